@@ -0,0 +1,83 @@
+use tree_sitter::Point;
+
+use crate::diagnostics::Diagnostic;
+
+// Edit replaces the code between start and end with text
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Edit {
+    pub start: Point,
+    pub end: Point,
+    pub text: String,
+}
+
+impl Edit {
+    // removal removes the code between start and end. When nothing else is on those lines the
+    // lines are removed as well.
+    pub fn removal(code: &str, start: Point, end: Point) -> Self {
+        let lines: Vec<&str> = code.split('\n').collect();
+        let before = lines
+            .get(start.row)
+            .and_then(|l| l.get(..start.column))
+            .is_some_and(|s| s.trim().is_empty());
+        let after = lines
+            .get(end.row)
+            .and_then(|l| l.get(end.column..))
+            .is_some_and(|s| s.trim().is_empty());
+        if before && after {
+            Edit {
+                start: Point {
+                    row: start.row,
+                    column: 0,
+                },
+                end: Point {
+                    row: end.row + 1,
+                    column: 0,
+                },
+                text: String::new(),
+            }
+        } else {
+            Edit {
+                start,
+                end,
+                text: String::new(),
+            }
+        }
+    }
+}
+
+// Action is a change of a document that fixes diagnostic
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Action {
+    pub title: String,
+    pub diagnostic: Diagnostic,
+    pub edits: Vec<Edit>,
+}
+
+#[cfg(test)]
+mod tests {
+    use tree_sitter::Point;
+
+    use super::Edit;
+
+    #[test]
+    fn removal() {
+        let code = "a = 1;\n  b = 2; c = 3;\n";
+        let point = |row, column| Point { row, column };
+        assert_eq!(
+            Edit::removal(code, point(0, 0), point(0, 6)),
+            Edit {
+                start: point(0, 0),
+                end: point(1, 0),
+                text: String::new(),
+            }
+        );
+        assert_eq!(
+            Edit::removal(code, point(1, 2), point(1, 8)),
+            Edit {
+                start: point(1, 2),
+                end: point(1, 8),
+                text: String::new(),
+            }
+        );
+    }
+}
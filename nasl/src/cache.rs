@@ -1,25 +1,337 @@
-use tracing::warn;
+use std::{
+    collections::{hash_map::DefaultHasher, BTreeMap, HashMap, HashSet},
+    error::Error,
+    fs,
+    hash::{Hash, Hasher},
+    path::Path,
+    sync::{
+        atomic::{AtomicBool, Ordering},
+        Arc,
+    },
+    thread::{self, JoinHandle},
+    time::SystemTime,
+};
 
-use crate::openvas_funcs::OpenVASInterpreter;
+use tracing::{debug, trace, warn};
+use tree_sitter::{Point, Tree};
+
+use crate::{
+    actions::{Action, Edit},
+    completion::{include_prefix, Completion, CompletionKind},
+    diagnostics::{
+        argument_diagnostics, circular_include, syntax_errors, undefined_function,
+        undefined_variable, unresolved_include, unused_include, unused_parameter, unused_variable,
+        Diagnostic,
+    },
+    document::Document,
+    index::{index, Index, Indexed},
+    interpret::{nasl_tree, NASLInterpreter, SearchParameter},
+    openvas_funcs::{Builtin, OpenVASInterpreter},
+    position::Encoding,
+    signature::{call_at, Call, Parameter, Signature},
+    types::{Argument, Definition, Identifier},
+    variables::{undefined_variables, unused_parameters, unused_variables, Usage},
+};
 
 #[derive(Debug)]
 pub struct Cache {
     pub paths: Vec<String>,
     internal: Option<OpenVASInterpreter>,
+    // documents contains each opened document by path
+    documents: HashMap<String, Document>,
+    // interpreters contains the last parsed interpreter of a path
+    interpreters: Interpreters,
+    // encoding is the position encoding negotiated with the client
+    encoding: Encoding,
+    // index contains the symbols of all files within paths once building finished
+    index: Option<Index>,
+    // building builds the index in the background until it is cancelled
+    building: Option<(JoinHandle<Index>, Arc<AtomicBool>)>,
+    // outdated contains the paths that changed since they got indexed
+    outdated: HashSet<String>,
+}
+
+// DEFAULT_LIMIT is the amount of interpreters that are kept when no limit is configured
+pub const DEFAULT_LIMIT: usize = 1000;
+// PIN_HITS is the amount of cache hits after which an include is pinned
+const PIN_HITS: usize = 2;
+// PINNED_SHARE divides the limit into the maximal amount of pinned entries
+const PINNED_SHARE: usize = 2;
+
+// Version is used to detect if a cached interpreter is outdated
+#[derive(Clone, Debug, PartialEq, Eq)]
+enum Version {
+    // Document is the hash of the code of an opened document
+    Document(u64),
+    // File is the modification time and length of a file on disk
+    File(SystemTime, u64),
+}
+
+#[derive(Clone, Debug)]
+struct Entry {
+    version: Version,
+    interpreter: NASLInterpreter,
+    // last_used is the tick of the last access and is used to find the least recently used entry
+    last_used: u64,
+    hits: usize,
+    // pinned entries are evicted after the other entries
+    pinned: bool,
+}
+
+impl Entry {
+    // frequent entries are includes that got used often enough to be pinned
+    fn frequent(&self, path: &str) -> bool {
+        path.ends_with(".inc") && self.hits >= PIN_HITS
+    }
+}
+
+// Interpreters is a least recently used cache of interpreters by path
+#[derive(Debug)]
+struct Interpreters {
+    entries: HashMap<String, Entry>,
+    // recent and pinned contain the paths of the unpinned and pinned entries by their last use
+    recent: BTreeMap<u64, String>,
+    pinned: BTreeMap<u64, String>,
+    limit: usize,
+    tick: u64,
+    hits: u64,
+    misses: u64,
+}
+
+// Statistics describes the current state of the interpreter cache
+#[derive(Clone, Debug, PartialEq)]
+pub struct Statistics {
+    pub entries: usize,
+    pub pinned: usize,
+    pub limit: usize,
+    pub hits: u64,
+    pub misses: u64,
+}
+
+impl Statistics {
+    pub fn hit_rate(&self) -> f64 {
+        match self.hits + self.misses {
+            0 => 0.0,
+            requests => self.hits as f64 / requests as f64,
+        }
+    }
+}
+
+impl Interpreters {
+    fn new(limit: usize) -> Self {
+        Self {
+            entries: HashMap::new(),
+            recent: BTreeMap::new(),
+            pinned: BTreeMap::new(),
+            limit,
+            tick: 0,
+            hits: 0,
+            misses: 0,
+        }
+    }
+
+    fn order(&mut self, pinned: bool) -> &mut BTreeMap<u64, String> {
+        match pinned {
+            true => &mut self.pinned,
+            false => &mut self.recent,
+        }
+    }
+
+    // get returns the cached interpreter of path when it is based on version
+    fn get(&mut self, path: &str, version: &Version) -> Option<NASLInterpreter> {
+        self.tick += 1;
+        let tick = self.tick;
+        let e = match self.entries.get_mut(path) {
+            Some(e) if &e.version == version => e,
+            _ => {
+                self.misses += 1;
+                return None;
+            }
+        };
+        let (last_used, was_pinned) = (e.last_used, e.pinned);
+        e.hits += 1;
+        e.last_used = tick;
+        e.pinned = e.pinned || e.frequent(path);
+        let (interpreter, pinned) = (e.interpreter.clone(), e.pinned);
+        self.hits += 1;
+        self.order(was_pinned).remove(&last_used);
+        self.order(pinned).insert(tick, path.to_string());
+        self.unpin();
+        Some(interpreter)
+    }
+
+    fn insert(&mut self, path: &str, version: Version, interpreter: NASLInterpreter) {
+        self.tick += 1;
+        let tick = self.tick;
+        let (hits, pinned) = match self.entries.remove(path) {
+            Some(e) => {
+                self.order(e.pinned).remove(&e.last_used);
+                (e.hits, e.pinned)
+            }
+            None => (0, false),
+        };
+        self.order(pinned).insert(tick, path.to_string());
+        self.entries.insert(
+            path.to_string(),
+            Entry {
+                version,
+                interpreter,
+                last_used: tick,
+                hits,
+                pinned,
+            },
+        );
+        self.evict();
+    }
+
+    // unpin moves the least recently used pinned entries to the other entries while they exceed
+    // their share of the limit
+    fn unpin(&mut self) {
+        while self.pinned.len() > self.limit / PINNED_SHARE {
+            if let Some((tick, p)) = self.pinned.pop_first() {
+                if let Some(e) = self.entries.get_mut(&p) {
+                    e.pinned = false;
+                }
+                self.recent.insert(tick, p);
+            }
+        }
+    }
+
+    // evict removes the least recently used entries until limit is reached; pinned entries are
+    // only removed when no other entry is left
+    fn evict(&mut self) {
+        self.unpin();
+        while self.entries.len() > self.limit {
+            let pinned = self.recent.is_empty();
+            match self.order(pinned).pop_first() {
+                Some((_, p)) => {
+                    trace!("evicting {p}");
+                    self.entries.remove(&p);
+                }
+                None => break,
+            }
+        }
+    }
+
+    fn statistics(&self) -> Statistics {
+        Statistics {
+            entries: self.entries.len(),
+            pinned: self.pinned.len(),
+            limit: self.limit,
+            hits: self.hits,
+            misses: self.misses,
+        }
+    }
+}
+
+fn is_script(path: &Path) -> bool {
+    matches!(
+        path.extension().and_then(|e| e.to_str()),
+        Some("nasl") | Some("inc")
+    )
 }
 
 impl Cache {
+    // update_paths adds the paths that are not known yet; the index is only built again when
+    // there is a new one
     pub fn update_paths(&mut self, paths: Vec<String>) {
-        self.paths.extend(paths);
+        let known = self.paths.len();
+        for p in paths {
+            if !self.paths.contains(&p) {
+                self.paths.push(p);
+            }
+        }
+        if self.paths.len() != known {
+            self.build_index();
+        }
+    }
+
+    // build_index starts building the index of paths in the background; until it is finished
+    // the previous index is dropped. A running build is cancelled before.
+    pub fn build_index(&mut self) {
+        if let Some((building, cancelled)) = self.building.take() {
+            cancelled.store(true, Ordering::Relaxed);
+            if building.join().is_err() {
+                warn!("the cancelled build of the index panicked");
+            }
+        }
+        let paths: Vec<String> = self.local_paths().map(|p| p.to_string()).collect();
+        let cancelled = Arc::new(AtomicBool::new(false));
+        let cancel = cancelled.clone();
+        self.index = None;
+        self.building = Some((
+            thread::spawn(move || {
+                let paths: Vec<&str> = paths.iter().map(|p| p.as_str()).collect();
+                Index::build(&paths, &cancel)
+            }),
+            cancelled,
+        ));
+        // the index is built from the disk, therefore opened documents are indexed again
+        self.outdated.extend(self.documents.keys().cloned());
+    }
+
+    // indexed returns the index when it is built; when wait is true a running build is awaited
+    fn indexed(&mut self, wait: bool) -> Option<&mut Index> {
+        if self.index.is_none() && self.building.is_none() {
+            self.build_index();
+        }
+        if let Some((building, cancelled)) = self.building.take() {
+            if !wait && !building.is_finished() {
+                self.building = Some((building, cancelled));
+                return None;
+            }
+            self.index = Some(building.join().unwrap_or_else(|_| {
+                warn!("unable to build the index of {:?}", self.paths);
+                Index::default()
+            }));
+        }
+        let outdated: Vec<String> = self.outdated.drain().collect();
+        for path in outdated {
+            let local =
+                is_script(Path::new(&path)) && self.local_paths().any(|p| path.starts_with(p));
+            let file = match self.read(&path) {
+                Ok(code) if local => Some(index(&path, &code)),
+                _ => None,
+            };
+            if let Some(index) = self.index.as_mut() {
+                index.update(&path, file);
+            }
+        }
+        self.index.as_mut()
     }
 
     pub fn new(paths: Vec<String>) -> Cache {
         Cache {
             paths,
             internal: None,
+            documents: HashMap::new(),
+            interpreters: Interpreters::new(DEFAULT_LIMIT),
+            encoding: Encoding::default(),
+            index: None,
+            building: None,
+            outdated: HashSet::new(),
         }
     }
 
+    pub fn set_encoding(&mut self, encoding: Encoding) {
+        self.encoding = encoding;
+    }
+
+    pub fn encoding(&self) -> Encoding {
+        self.encoding
+    }
+
+    // set_limit sets the maximal amount of cached interpreters.
+    // Frequently used includes are kept longer as long as they take at most half of the limit.
+    pub fn set_limit(&mut self, limit: usize) {
+        self.interpreters.limit = limit;
+        self.interpreters.evict();
+    }
+
+    pub fn statistics(&self) -> Statistics {
+        self.interpreters.statistics()
+    }
+
     pub fn set_internal(&mut self, path: &str) {
         let vp = if path.ends_with(".c") {
             path.to_string()
@@ -35,4 +347,1477 @@ impl Cache {
     pub fn internal(&mut self) -> Option<OpenVASInterpreter> {
         self.internal.clone()
     }
+
+    pub fn builtin(&self, name: &str) -> Option<&Builtin> {
+        self.internal.as_ref().and_then(|i| i.builtin(name))
+    }
+
+    // open stores the code of an opened or replaced document so that it is used instead of the
+    // content on disk
+    pub fn open(&mut self, path: &str, code: String) {
+        self.documents.insert(path.to_string(), Document::new(code));
+        self.outdate(path);
+    }
+
+    pub fn close(&mut self, path: &str) {
+        self.documents.remove(path);
+        self.outdate(path);
+    }
+
+    // outdate marks path to be indexed again before the next search; e.g. when it got changed,
+    // created or deleted
+    pub fn outdate(&mut self, path: &str) {
+        self.outdated.insert(path.to_string());
+    }
+
+    // workspace_symbols returns the functions of all .inc files and the oids and names of all
+    // .nasl files within paths fuzzy matching query. While the index is built nothing is found.
+    // Changed documents are indexed again before searching.
+    pub fn workspace_symbols(&mut self, query: &str) -> Vec<Indexed> {
+        let result: Vec<Indexed> = match self.indexed(false) {
+            Some(index) => index.search(query).into_iter().cloned().collect(),
+            None => {
+                debug!("index is not built yet");
+                vec![]
+            }
+        };
+        debug!("found {} workspace symbols for {query}", result.len());
+        result
+    }
+
+    pub fn document(&self, path: &str) -> Option<&Document> {
+        self.documents.get(path)
+    }
+
+    pub fn document_mut(&mut self, path: &str) -> Option<&mut Document> {
+        self.documents.get_mut(path)
+    }
+
+    // read returns the code of an opened document or loads it from disk
+    pub fn read(&self, path: &str) -> Result<String, std::io::Error> {
+        match self.documents.get(path) {
+            Some(d) => Ok(d.code.clone()),
+            None => NASLInterpreter::read(path),
+        }
+    }
+
+    fn version(&self, path: &str) -> Result<Version, std::io::Error> {
+        if let Some(d) = self.documents.get(path) {
+            let mut hasher = DefaultHasher::new();
+            d.code.hash(&mut hasher);
+            return Ok(Version::Document(hasher.finish()));
+        }
+        let meta = fs::metadata(path)?;
+        Ok(Version::File(meta.modified()?, meta.len()))
+    }
+
+    // interpreter returns the interpreter of path.
+    // The interpreter is only parsed again when the file or opened document changed.
+    pub fn interpreter(&mut self, path: &str) -> Result<NASLInterpreter, Box<dyn Error>> {
+        let version = self.version(path)?;
+        if let Some(i) = self.interpreters.get(path, &version) {
+            return Ok(i);
+        }
+        trace!("parsing {path}");
+        let interpreter = match self.documents.get(path) {
+            Some(d) => match d.tree() {
+                Some(tree) => NASLInterpreter::from_tree(path, &d.code, tree),
+                None => NASLInterpreter::new(path, &d.code)?,
+            },
+            None => NASLInterpreter::new(path, &self.read(path)?)?,
+        };
+        self.interpreters.insert(path, version, interpreter.clone());
+        Ok(interpreter)
+    }
+
+    // diagnostics returns the diagnostics of an opened document
+    pub fn diagnostics(&mut self, path: &str) -> Vec<Diagnostic> {
+        let mut result = match self.documents.get(path) {
+            Some(d) => d
+                .tree()
+                .map(|t| syntax_errors(&d.code, &t.root_node()))
+                .unwrap_or_default(),
+            None => return vec![],
+        };
+        result.extend(self.include_diagnostics(path));
+        result.extend(self.argument_diagnostics(path));
+        result.extend(
+            self.undefined_functions(path)
+                .iter()
+                .map(undefined_function),
+        );
+        result.extend(self.variable_diagnostics(path));
+        result.extend(self.unused_diagnostics(path));
+        result.extend(
+            self.unused_includes(path)
+                .iter()
+                .map(|(_, include)| unused_include(include)),
+        );
+        result
+    }
+
+    // unused_diagnostics returns a warning for each local variable and parameter of the
+    // functions of path that is never read
+    fn unused_diagnostics(&mut self, path: &str) -> Vec<Diagnostic> {
+        let interprets = self.interpreters(path);
+        let interpreter = match interprets.first() {
+            Some(i) => i,
+            None => return vec![],
+        };
+        // writes to globals of includes are no unused local variables
+        let included: HashSet<&str> = interprets[1..].iter().flat_map(|i| i.globals()).collect();
+        let scopes = interpreter.scopes();
+        let mut result: Vec<Diagnostic> = unused_variables(scopes, &|n| included.contains(n))
+            .into_iter()
+            .map(|u| unused_variable(&u.first().id))
+            .collect();
+        result.extend(unused_parameters(scopes).into_iter().map(unused_parameter));
+        result.sort_by_key(|d| d.start);
+        result
+    }
+
+    // variable_diagnostics returns a warning for each read of a variable of path that is neither
+    // assigned before, defined by an include nor predefined by the interpreter.
+    // Without builtins or configured paths includes and constants are unknown, therefore nothing
+    // is checked. Includes rely on the variables of their includers, therefore they are not
+    // checked either.
+    fn variable_diagnostics(&mut self, path: &str) -> Vec<Diagnostic> {
+        if self.internal.is_none() || self.paths.is_empty() || path.ends_with(".inc") {
+            return vec![];
+        }
+        let interprets = self.interpreters(path);
+        let interpreter = match interprets.first() {
+            Some(i) => i,
+            None => return vec![],
+        };
+        let included: HashSet<&str> = interprets[1..].iter().flat_map(|i| i.globals()).collect();
+        let known = |name: &str| {
+            included.contains(name) || self.internal.as_ref().is_some_and(|i| i.is_constant(name))
+        };
+        let scopes = interpreter.scopes();
+        let mut names: Vec<&str> = scopes
+            .iter()
+            .flat_map(|s| {
+                s.writes()
+                    .chain(s.parameters.iter().filter_map(|p| p.identifier.as_deref()))
+            })
+            .chain(included.iter().copied())
+            .collect();
+        names.sort_unstable();
+        names.dedup();
+        undefined_variables(scopes, &known)
+            .iter()
+            .map(|id| {
+                let assigned_later = scopes.first().is_some_and(|top| {
+                    top.variables.iter().any(|v| {
+                        v.usage != Usage::Read
+                            && v.id.start > id.start
+                            && v.id.identifier == id.identifier
+                    })
+                });
+                undefined_variable(id, assigned_later, &names)
+            })
+            .collect()
+    }
+
+    // undefined_functions returns each call of path to a function that is neither defined within
+    // path or its includes nor a builtin.
+    // Without builtins or configured paths nearly every call would be reported, therefore nothing
+    // is checked. Includes rely on the functions of the other includes of their includers,
+    // therefore they are not checked either.
+    fn undefined_functions(&mut self, path: &str) -> Vec<Identifier> {
+        if self.internal.is_none() || self.paths.is_empty() || path.ends_with(".inc") {
+            return vec![];
+        }
+        let interprets = self.interpreters(path);
+        let interpreter = match interprets.first() {
+            Some(i) => i,
+            None => return vec![],
+        };
+        let mut result: Vec<Identifier> = interpreter
+            .all_calls()
+            .map(|(id, _)| id)
+            .filter(|id| {
+                let name = id.identifier.clone().unwrap_or_default();
+                // include is handled by the interpreter itself
+                name != "include"
+                    && self.builtin(&name).is_none()
+                    && !interprets.iter().any(|i| i.function(&name).is_some())
+            })
+            .cloned()
+            .collect();
+        result.sort_by_key(|id| id.start);
+        result
+    }
+
+    // code_actions returns the fixes for the diagnostics of path between start and end
+    pub fn code_actions(&mut self, path: &str, start: Point, end: Point) -> Vec<Action> {
+        let within = |id: &Identifier| id.start <= end && start <= id.end;
+        let mut result = vec![];
+        let interprets = self.interpreters(path);
+        if let Some(interpreter) = interprets.first() {
+            let code = self.read(path).unwrap_or_default();
+            let included: HashSet<&str> =
+                interprets[1..].iter().flat_map(|i| i.globals()).collect();
+            for u in unused_variables(interpreter.scopes(), &|n| included.contains(n)) {
+                if !u.usages.iter().any(|v| within(&v.id)) {
+                    continue;
+                }
+                let first = u.first();
+                let name = first.id.identifier.clone().unwrap_or_default();
+                // each dead write is removed; a declaration only when it declares nothing else
+                let edits: Vec<Edit> = u
+                    .usages
+                    .iter()
+                    .filter_map(|v| v.removable)
+                    .map(|(from, to)| Edit::removal(&code, from, to))
+                    .collect();
+                let title = match edits.len() {
+                    0 => continue,
+                    _ if first.usage == Usage::Local && first.removable.is_some() => {
+                        format!("Remove unused variable '{name}'")
+                    }
+                    1 => format!("Remove unused assignment to '{name}'"),
+                    _ => format!("Remove unused assignments to '{name}'"),
+                };
+                result.push(Action {
+                    title,
+                    diagnostic: unused_variable(&first.id),
+                    edits,
+                });
+            }
+        }
+        let unused: Vec<(Identifier, Identifier)> = self
+            .unused_includes(path)
+            .into_iter()
+            .filter(|(_, include)| within(include))
+            .collect();
+        if let (false, Some((code, tree))) = (unused.is_empty(), self.parsed(path)) {
+            let root = tree.root_node();
+            for (call, include) in unused {
+                // the include call is removed with its statement
+                let statement = root
+                    .descendant_for_point_range(call.start, call.end)
+                    .and_then(|n| {
+                        std::iter::successors(Some(n), |n| n.parent())
+                            .find(|n| n.kind() == "expression_statement")
+                    });
+                let (from, to) = match statement {
+                    Some(s) => (s.start_position(), s.end_position()),
+                    None => continue,
+                };
+                result.push(Action {
+                    title: format!(
+                        "Remove include(\"{}\")",
+                        include.identifier.clone().unwrap_or_default()
+                    ),
+                    diagnostic: unused_include(&include),
+                    edits: vec![Edit::removal(&code, from, to)],
+                });
+            }
+        }
+        // finding undefined functions requires the includes, therefore only calls within the
+        // range are checked
+        let calls = self
+            .interpreter(path)
+            .map(|i| i.all_calls().any(|(id, _)| within(id)))
+            .unwrap_or_default();
+        if !calls {
+            return result;
+        }
+        let undefined: Vec<Identifier> = self
+            .undefined_functions(path)
+            .into_iter()
+            .filter(|id| within(id))
+            .collect();
+        if undefined.is_empty() {
+            return result;
+        }
+        let position = self.include_position(path);
+        for id in undefined {
+            let name = id.identifier.clone().unwrap_or_default();
+            for include in self.defining_includes(&name) {
+                result.push(Action {
+                    title: format!("Add include(\"{include}\")"),
+                    diagnostic: undefined_function(&id),
+                    edits: vec![Edit {
+                        start: position,
+                        end: position,
+                        text: format!("include(\"{include}\");\n"),
+                    }],
+                });
+            }
+        }
+        result
+    }
+
+    // include_position returns the position a new include is added to path. This is the line
+    // after the last include or, without includes, after the description block.
+    fn include_position(&mut self, path: &str) -> Point {
+        let last_include = self
+            .interpreter(path)
+            .ok()
+            .and_then(|i| i.calls("include").map(|(id, _)| id.end.row).max());
+        if let Some(row) = last_include {
+            return Point {
+                row: row + 1,
+                column: 0,
+            };
+        }
+        let (code, tree) = match self.parsed(path) {
+            Some(p) => p,
+            None => return Point::default(),
+        };
+        let root = tree.root_node();
+        let crsr = &mut root.walk();
+        let description = root
+            .named_children(crsr)
+            .filter(|c| c.kind() == "if_statement")
+            .find(|c| {
+                c.child_by_field_name("condition")
+                    .map(|c| code[c.byte_range()].contains("description"))
+                    .unwrap_or_default()
+            });
+        match description {
+            Some(d) => Point {
+                row: d.end_position().row + 1,
+                column: 0,
+            },
+            None => Point::default(),
+        }
+    }
+
+    // parsed returns the code of path and its tree; the tree of an opened document is reused
+    fn parsed(&self, path: &str) -> Option<(String, Tree)> {
+        let code = self.read(path).ok()?;
+        let tree = match self.documents.get(path).and_then(|d| d.tree()) {
+            Some(t) => t.clone(),
+            None => nasl_tree(&code, None).ok()?,
+        };
+        Some((code, tree))
+    }
+
+    // unused_includes returns the call and the name of each include of path whose file, including
+    // the files it includes that are not already included by an earlier include, defines neither
+    // a function nor a global variable used by path or by one of its other includes. Includes
+    // often rely on files included by the script, therefore those usages count as well.
+    // Without configured paths includes are not found, therefore nothing is checked.
+    fn unused_includes(&mut self, path: &str) -> Vec<(Identifier, Identifier)> {
+        if self.paths.is_empty() {
+            return vec![];
+        }
+        let interprets = self.interpreters(path);
+        let interpreter = match interprets.first() {
+            Some(i) => i,
+            None => return vec![],
+        };
+        let paths = self.paths.clone();
+        // each include with the origins of the files it includes
+        let mut includes = vec![];
+        for (call, arguments) in interpreter.calls("include") {
+            let include = match arguments.into_iter().find_map(|a| match a {
+                Argument::StringLiteral(id) => Some(id),
+                _ => None,
+            }) {
+                Some(i) => i,
+                None => continue,
+            };
+            let resolved =
+                match self.resolve_include(&include.identifier.clone().unwrap_or_default()) {
+                    Some(r) => r,
+                    None => continue,
+                };
+            let mut load = |p: &str| self.interpreter(p);
+            if let Ok(included) =
+                NASLInterpreter::new_with_includes(&resolved, paths.clone(), &mut load)
+            {
+                let origins: Vec<String> = included.into_iter().map(|i| i.origin()).collect();
+                includes.push((call, include, origins));
+            }
+        }
+        let mut result = vec![];
+        for (i, (call, include, origins)) in includes.iter().enumerate() {
+            let provided = |origin: &String| {
+                includes
+                    .iter()
+                    .enumerate()
+                    .any(|(j, (_, _, o))| j < i && o.contains(origin))
+            };
+            let own: Vec<&String> = origins.iter().filter(|o| !provided(o)).collect();
+            let (own, others): (Vec<&NASLInterpreter>, Vec<&NASLInterpreter>) = interprets[1..]
+                .iter()
+                .partition(|i| own.contains(&&(*i).clone().origin()));
+            let used: HashSet<&str> = others
+                .into_iter()
+                .chain(interprets.first())
+                .flat_map(|i| i.used_names())
+                .collect();
+            let defines = own
+                .iter()
+                .any(|i| i.functions().chain(i.globals()).any(|n| used.contains(n)));
+            if !defines {
+                result.push((call.clone(), include.clone()));
+            }
+        }
+        result
+    }
+
+    // defining_includes returns the name of each .inc file within paths that defines the
+    // function name. While the index is built nothing is found.
+    pub fn defining_includes(&mut self, name: &str) -> Vec<String> {
+        let paths: Vec<String> = match self.indexed(false) {
+            Some(index) => index.functions(name).map(|s| s.path.clone()).collect(),
+            None => return vec![],
+        };
+        let mut result: Vec<String> = paths.iter().filter_map(|p| self.include_name(p)).collect();
+        result.sort();
+        result.dedup();
+        result
+    }
+
+    // argument_diagnostics returns a warning for each call of path that uses a named argument
+    // the callee does not know or that misses a required argument.
+    // Builtins are only checked when their implementation got parsed; unknown arguments only
+    // when all of their arguments got resolved.
+    fn argument_diagnostics(&mut self, path: &str) -> Vec<Diagnostic> {
+        let interprets = self.interpreters(path);
+        let interpreter = match interprets.first() {
+            Some(i) => i,
+            None => return vec![],
+        };
+        interpreter
+            .all_calls()
+            .flat_map(|(id, arguments)| {
+                let name = id.identifier.clone().unwrap_or_default();
+                let (signature, complete) = match self.function_signature(&interprets, &name) {
+                    Some(s) => (s, true),
+                    None => match self.builtin(&name) {
+                        Some(b) if b.source.is_some() => (b.signature(), b.resolved),
+                        _ => return vec![],
+                    },
+                };
+                argument_diagnostics(id, arguments, &signature, complete)
+            })
+            .collect()
+    }
+
+    // include_diagnostics returns an error for each include of path that is not found.
+    // Without configured paths every include would be reported, therefore nothing is checked.
+    fn include_diagnostics(&mut self, path: &str) -> Vec<Diagnostic> {
+        if self.paths.is_empty() {
+            return vec![];
+        }
+        let interpreter = match self.interpreter(path) {
+            Ok(i) => i,
+            Err(_) => return vec![],
+        };
+        let includes: Vec<(Identifier, Option<String>)> = interpreter
+            .calls("include")
+            .flat_map(|(_, args)| args)
+            .filter_map(|a| match a {
+                Argument::StringLiteral(id) => {
+                    let resolved = self.resolve_include(&id.identifier.clone().unwrap_or_default());
+                    Some((id, resolved))
+                }
+                _ => None,
+            })
+            .collect();
+        let searched: Vec<&str> = self.local_paths().collect();
+        let mut result: Vec<Diagnostic> = includes
+            .iter()
+            .filter(|(_, resolved)| resolved.is_none())
+            .map(|(id, _)| {
+                let name = id.identifier.clone().unwrap_or_default();
+                unresolved_include(id, &searched, &self.similar_includes(&name))
+            })
+            .collect();
+        let paths = self.paths.clone();
+        let mut load = |p: &str| self.interpreter(p);
+        let cycles = NASLInterpreter::include_cycles(path, paths, &mut load).unwrap_or_default();
+        for cycle in cycles {
+            let chain: Vec<String> = cycle
+                .iter()
+                .map(|p| self.include_name(p).unwrap_or_else(|| p.clone()))
+                .collect();
+            // the cycle is reported on the include of path that starts the chain
+            result.extend(
+                includes
+                    .iter()
+                    .filter(|(_, resolved)| resolved.as_ref() == cycle.get(1))
+                    .map(|(id, _)| circular_include(id, &chain)),
+            );
+        }
+        result
+    }
+
+    // resolve_include returns the path of the first configured path containing name
+    pub fn resolve_include(&self, name: &str) -> Option<String> {
+        self.local_paths()
+            .map(|p| format!("{p}/{name}"))
+            .find(|p| self.documents.contains_key(p) || Path::new(p).exists())
+    }
+
+    // similar_includes returns existing include names that differ from name only in case or in
+    // using .nasl instead of .inc and vice versa
+    fn similar_includes(&self, name: &str) -> Vec<String> {
+        let wanted = Path::new(name);
+        let stem = match wanted.file_stem().and_then(|s| s.to_str()) {
+            Some(s) => s.to_lowercase(),
+            None => return vec![],
+        };
+        let parent = wanted.parent().unwrap_or_else(|| Path::new(""));
+        let mut result: Vec<String> = self
+            .local_paths()
+            .filter_map(|p| fs::read_dir(Path::new(p).join(parent)).ok())
+            .flat_map(|entries| entries.filter_map(|e| e.ok()))
+            .map(|e| e.path())
+            .filter(|p| is_script(p))
+            .filter(|p| {
+                p.file_stem()
+                    .and_then(|s| s.to_str())
+                    .map(|s| s.to_lowercase() == stem)
+                    .unwrap_or_default()
+            })
+            .filter_map(|p| {
+                p.file_name()
+                    .and_then(|f| parent.join(f).to_str().map(|s| s.to_string()))
+            })
+            .filter(|s| s != name)
+            .collect();
+        result.sort();
+        result.dedup();
+        result
+    }
+
+    // search_parameter returns the identifier at line and column of code while reusing the tree
+    // of an opened document
+    pub fn search_parameter<'a>(
+        &self,
+        path: &'a str,
+        code: &'a str,
+        line: usize,
+        column: usize,
+    ) -> Option<SearchParameter<'a>> {
+        match self.documents.get(path).and_then(|d| d.tree()) {
+            Some(tree) => NASLInterpreter::search_parameter_in_tree(path, code, tree, line, column),
+            None => NASLInterpreter::search_parameter(path, code, line, column),
+        }
+    }
+
+    // call_at returns the call whose arguments contain line and column of code while reusing the
+    // tree of an opened document
+    pub fn call_at(&self, path: &str, code: &str, line: usize, column: usize) -> Option<Call> {
+        let point = Point { row: line, column };
+        match self.documents.get(path).and_then(|d| d.tree()) {
+            Some(tree) => call_at(code, &tree.root_node(), point),
+            None => {
+                let tree = nasl_tree(code, None).ok()?;
+                call_at(code, &tree.root_node(), point)
+            }
+        }
+    }
+
+    // signature returns the signature of the function called at line and column of path as well
+    // as the call itself. Functions defined within path or its includes shadow builtins.
+    pub fn signature(
+        &mut self,
+        path: &str,
+        line: usize,
+        column: usize,
+    ) -> Option<(Signature, Call)> {
+        let code = self.read(path).ok()?;
+        let call = self.call_at(path, &code, line, column)?;
+        let interprets = self.interpreters(path);
+        let signature = match self.function_signature(&interprets, &call.name) {
+            Some(s) => s,
+            None => self.builtin(&call.name)?.signature(),
+        };
+        Some((signature, call))
+    }
+
+    // function_signature returns the signature of the user defined function name within the
+    // given interpreters
+    fn function_signature(&self, interprets: &[NASLInterpreter], name: &str) -> Option<Signature> {
+        let (interpreter, (id, parameters)) = interprets
+            .iter()
+            .find_map(|i| i.function(name).map(|f| (i, f)))?;
+        let required = interpreter.required_parameters(name);
+        let origin = interpreter.clone().origin();
+        let documentation = self
+            .read(&origin)
+            .ok()
+            .and_then(|c| NASLInterpreter::leading_comment(&c, id.start.row));
+        Some(Signature {
+            name: name.to_string(),
+            parameters: parameters
+                .into_iter()
+                .filter_map(|p| p.identifier)
+                .map(|name| Parameter {
+                    required: required.contains(&name),
+                    name,
+                    named: true,
+                })
+                .collect(),
+            documentation,
+        })
+    }
+
+    // local_paths returns the configured paths without a file:// prefix
+    fn local_paths(&self) -> impl Iterator<Item = &str> + '_ {
+        self.paths
+            .iter()
+            .map(|p| p.strip_prefix("file://").unwrap_or(p))
+    }
+
+    // interpreters returns the interpreter of path followed by the interpreters of all includes
+    pub fn interpreters(&mut self, path: &str) -> Vec<NASLInterpreter> {
+        let paths = self.paths.clone();
+        let mut load = |p: &str| self.interpreter(p);
+        match NASLInterpreter::new_with_includes(path, paths, &mut load) {
+            Ok(i) => {
+                debug!("found {} interpreter", i.len());
+                i
+            }
+            Err(err) => {
+                warn!("no interpreter found for {path}: {err}");
+                vec![]
+            }
+        }
+    }
+
+    // definitions returns the origin and start of each definition of sp.
+    // When sp is not defined within the given interpreters the internal functions are used.
+    pub fn definitions(
+        &self,
+        interprets: &[NASLInterpreter],
+        sp: &SearchParameter,
+    ) -> Vec<(String, Point)> {
+        let foreign = Self::foreign_definitions(interprets, sp.origin, sp.name);
+        self.resolve_definitions(interprets, sp, &foreign)
+    }
+
+    // foreign_definitions returns the origin and start of each definition of name within the
+    // files other than origin. They do not depend on the position of a usage within origin,
+    // therefore they are resolved once for all usages of a file.
+    pub fn foreign_definitions(
+        interprets: &[NASLInterpreter],
+        origin: &str,
+        name: &str,
+    ) -> Vec<(String, Point)> {
+        let sp = SearchParameter {
+            origin,
+            name,
+            pos: Point::default(),
+        };
+        interprets
+            .iter()
+            .map(|i| (i.clone().origin(), i))
+            .filter(|(o, _)| o != origin)
+            .flat_map(|(o, i)| {
+                i.find_points(&sp)
+                    .map(|p| (o.clone(), p))
+                    .collect::<Vec<(String, Point)>>()
+            })
+            .collect()
+    }
+
+    // resolve_definitions returns the definitions of sp like definitions does using the already
+    // resolved foreign definitions of the file of sp
+    pub fn resolve_definitions(
+        &self,
+        interprets: &[NASLInterpreter],
+        sp: &SearchParameter,
+        foreign: &[(String, Point)],
+    ) -> Vec<(String, Point)> {
+        let own: Vec<&NASLInterpreter> = interprets
+            .iter()
+            .filter(|i| (*i).clone().origin() == sp.origin)
+            .collect();
+        // a local variable is only defined within the file of sp
+        let local = own.iter().any(|i| i.declares_local(sp));
+        let mut found: Vec<(String, Point)> = own
+            .iter()
+            .flat_map(|i| i.find_points(sp).map(|p| (sp.origin.to_string(), p)))
+            .collect();
+        if !local {
+            found.extend(foreign.iter().cloned());
+        }
+        if found.is_empty() {
+            if let Some(i) = &self.internal {
+                return i.find_origin_location(sp).collect();
+            }
+        }
+        found
+    }
+
+    // include_name returns the name used within an include statement to load path
+    pub fn include_name(&self, path: &str) -> Option<String> {
+        self.local_paths()
+            .filter_map(|p| Path::new(path).strip_prefix(p).ok())
+            .filter_map(|n| n.to_str())
+            .find(|n| !n.is_empty())
+            .map(|n| n.to_string())
+    }
+
+    // completions returns the proposals at line and column of path. Within the string literal of
+    // an include call these are the include names within paths, otherwise the missing named
+    // arguments of a surrounding call and the definitions visible at the cursor followed by the
+    // builtin functions.
+    pub fn completions(&mut self, path: &str, line: usize, column: usize) -> Vec<Completion> {
+        let code = match self.read(path) {
+            Ok(c) => c,
+            Err(err) => {
+                warn!("unable to load {path}: {err}");
+                return vec![];
+            }
+        };
+        let text = code.lines().nth(line).unwrap_or_default();
+        // a column beyond the line or within a character is cut to the preceding character
+        let cut = (0..=column.min(text.len()))
+            .rev()
+            .find(|c| text.is_char_boundary(*c))
+            .unwrap_or_default();
+        let before = &text[..cut];
+        if let Some(prefix) = include_prefix(before) {
+            let start = Point {
+                row: line,
+                column: cut - prefix.len(),
+            };
+            let end = Point {
+                row: line,
+                column: cut,
+            };
+            return self.include_completions(prefix, (start, end));
+        }
+        let mut result: Vec<Completion> = vec![];
+        if let Some((signature, call)) = self.signature(path, line, column) {
+            if call.argument.is_none() {
+                result.extend(
+                    signature
+                        .parameters
+                        .iter()
+                        .filter(|p| p.named && !call.named.contains(&p.name))
+                        .map(|p| Completion {
+                            label: p.label(),
+                            kind: CompletionKind::Argument,
+                            detail: Some(signature.label()),
+                            replace: None,
+                        }),
+                );
+            }
+        }
+        for (i, interpreter) in self.interpreters(path).iter().enumerate() {
+            // only the outer scope of includes is visible
+            let pos = (i == 0).then_some(Point { row: line, column });
+            for d in interpreter.visible_definitions(pos) {
+                let label = match &d.identifier().identifier {
+                    Some(l) => l.clone(),
+                    None => continue,
+                };
+                let (kind, detail) = match &d {
+                    Definition::Function(_, params) => {
+                        let params: Vec<String> =
+                            params.iter().filter_map(|p| p.identifier.clone()).collect();
+                        (
+                            CompletionKind::Function,
+                            Some(format!("function {label}({})", params.join(", "))),
+                        )
+                    }
+                    Definition::Variable(_) => (CompletionKind::Variable, None),
+                };
+                result.push(Completion {
+                    label,
+                    kind,
+                    detail,
+                    replace: None,
+                });
+            }
+        }
+        if let Some(internal) = &self.internal {
+            result.extend(internal.builtins().map(|b| Completion {
+                label: b.name.clone(),
+                kind: CompletionKind::Builtin,
+                detail: Some("builtin".to_string()),
+                replace: None,
+            }));
+        }
+        // user defined functions shadow builtins and inner definitions shadow outer ones
+        let mut seen = HashSet::new();
+        result.retain(|c| seen.insert(c.label.clone()));
+        debug!("found {} completions for {path}", result.len());
+        result
+    }
+
+    // include_completions returns the names of the indexed .inc files starting with prefix; while
+    // the index is built there are none
+    fn include_completions(&mut self, prefix: &str, replace: (Point, Point)) -> Vec<Completion> {
+        let paths: Vec<String> = match self.indexed(false) {
+            Some(index) => index.includes().map(|p| p.to_string()).collect(),
+            None => vec![],
+        };
+        let mut names: Vec<String> = paths
+            .iter()
+            .filter_map(|p| self.include_name(p))
+            .filter(|n| n.starts_with(prefix))
+            .collect();
+        names.sort();
+        names.dedup();
+        names
+            .into_iter()
+            .map(|label| Completion {
+                label,
+                kind: CompletionKind::Include,
+                detail: None,
+                replace: Some(replace),
+            })
+            .collect()
+    }
+
+    // includers returns each script within paths that includes path directly or via other
+    // includes. A running build of the index is awaited since missing includers would silently
+    // omit references.
+    pub fn includers(&mut self, path: &str) -> Vec<String> {
+        let mut result: Vec<String> = vec![];
+        let mut names: Vec<String> = self.include_name(path).into_iter().collect();
+        while let Some(name) = names.pop() {
+            let found: Vec<String> = match self.indexed(true) {
+                Some(index) => index.includers(&name).map(|p| p.to_string()).collect(),
+                None => break,
+            };
+            for p in found {
+                if p == path || result.contains(&p) {
+                    continue;
+                }
+                if p.ends_with(".inc") {
+                    names.extend(self.include_name(&p));
+                }
+                result.push(p);
+            }
+        }
+        result.sort();
+        result
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use tree_sitter::Point;
+
+    use crate::{
+        actions::Edit,
+        completion::{Completion, CompletionKind},
+    };
+
+    use super::Cache;
+
+    #[test]
+    fn includers() {
+        let root = format!("{}/../.test-data", env!("CARGO_MANIFEST_DIR"));
+        let mut cache = Cache::new(vec![format!("file://{root}")]);
+        let inc = format!("{root}/example.inc");
+        assert_eq!(cache.include_name(&inc), Some("example.inc".to_string()));
+        assert_eq!(cache.includers(&inc), vec![format!("{root}/example2.nasl")]);
+    }
+
+    #[test]
+    fn transitive_includers() {
+        let dir = tempfile::tempdir().unwrap();
+        let root = dir.path().to_path_buf();
+        let files = [
+            ("a.inc", "function a() {}\n"),
+            ("b.inc", "include(\"a.inc\");\ninclude(\"c.inc\");\n"),
+            ("c.inc", "include(\"b.inc\");\n"),
+            ("d.nasl", "include(\"c.inc\");\na();\n"),
+            ("e.nasl", "a();\n"),
+        ];
+        for (name, code) in files {
+            std::fs::write(root.join(name), code).unwrap();
+        }
+        let root = root.to_str().unwrap().to_string();
+        let mut cache = Cache::new(vec![root.clone()]);
+        // circular includes are followed only once
+        assert_eq!(
+            cache.includers(&format!("{root}/a.inc")),
+            vec![
+                format!("{root}/b.inc"),
+                format!("{root}/c.inc"),
+                format!("{root}/d.nasl"),
+            ]
+        );
+        // the include graph follows opened documents
+        let path = format!("{root}/e.nasl");
+        cache.open(&path, "include(\"b.inc\");\na();\n".to_string());
+        assert_eq!(cache.includers(&format!("{root}/a.inc")).len(), 4);
+    }
+
+    #[test]
+    fn documents() {
+        let root = format!("{}/../.test-data", env!("CARGO_MANIFEST_DIR"));
+        let mut cache = Cache::new(vec![root.clone()]);
+        let path = format!("{root}/unsaved.nasl");
+        cache.open(&path, "include(\"example.inc\");\ntest(1);".to_string());
+        assert!(cache.read(&path).is_ok());
+        let interprets = cache.interpreters(&path);
+        assert_eq!(interprets.len(), 2);
+        cache.close(&path);
+        assert!(cache.read(&path).is_err());
+    }
+
+    #[test]
+    fn cached_interpreters() {
+        let mut cache = Cache::new(vec![]);
+        let path = "/tmp/cached.nasl";
+        cache.open(path, "a = 1;".to_string());
+        let interpreter = cache.interpreter(path).unwrap();
+        assert_eq!(interpreter.find_references("a").count(), 1);
+        let version = cache.interpreters.entries[path].version.clone();
+        let interpreter = cache.interpreter(path).unwrap();
+        assert_eq!(interpreter.find_references("a").count(), 1);
+        assert_eq!(cache.interpreters.entries[path].version, version);
+        let document = cache.document_mut(path).unwrap();
+        document.edit(0, 1, "b");
+        document.reparse();
+        let interpreter = cache.interpreter(path).unwrap();
+        assert_eq!(interpreter.find_references("b").count(), 1);
+        assert_ne!(cache.interpreters.entries[path].version, version);
+        assert_eq!(cache.interpreters.entries.len(), 1);
+    }
+
+    #[test]
+    fn evict_least_recently_used() {
+        let mut cache = Cache::new(vec![]);
+        cache.set_limit(2);
+        for p in ["/tmp/a.inc", "/tmp/b.nasl", "/tmp/c.nasl", "/tmp/d.nasl"] {
+            cache.open(p, "a = 1;".to_string());
+        }
+        for p in ["/tmp/a.inc", "/tmp/a.inc", "/tmp/a.inc", "/tmp/b.nasl"] {
+            cache.interpreter(p).unwrap();
+        }
+        // a.inc is pinned since it got used more than once
+        cache.interpreter("/tmp/c.nasl").unwrap();
+        cache.interpreter("/tmp/d.nasl").unwrap();
+        let mut cached: Vec<&String> = cache.interpreters.entries.keys().collect();
+        cached.sort();
+        assert_eq!(cached, vec!["/tmp/a.inc", "/tmp/d.nasl"]);
+        let statistics = cache.statistics();
+        assert_eq!(statistics.pinned, 1);
+        assert_eq!(statistics.hits, 2);
+        assert_eq!(statistics.misses, 4);
+        assert_eq!(statistics.hit_rate(), 2.0 / 6.0);
+    }
+
+    #[test]
+    fn limit_pinned_includes() {
+        let mut cache = Cache::new(vec![]);
+        cache.set_limit(4);
+        let includes: Vec<String> = (0..6).map(|i| format!("/tmp/pinned{i}.inc")).collect();
+        for p in &includes {
+            cache.open(p, "a = 1;".to_string());
+            for _ in 0..3 {
+                cache.interpreter(p).unwrap();
+            }
+        }
+        cache.open("/tmp/script.nasl", "a = 1;".to_string());
+        cache.interpreter("/tmp/script.nasl").unwrap();
+        // pinned includes take at most half of the limit
+        let statistics = cache.statistics();
+        assert_eq!(statistics.entries, 4);
+        assert_eq!(statistics.pinned, 2);
+        assert!(cache.interpreters.entries.contains_key("/tmp/script.nasl"));
+        assert!(cache.interpreters.entries.contains_key(&includes[5]));
+        assert!(!cache.interpreters.entries.contains_key(&includes[0]));
+    }
+
+    #[test]
+    fn unresolved_includes() {
+        let root = format!("{}/../.test-data", env!("CARGO_MANIFEST_DIR"));
+        let mut cache = Cache::new(vec![root.clone()]);
+        let path = format!("{root}/unsaved.nasl");
+        let code = "include(\"example.inc\");\ninclude(\"Example.nasl\");\n\
+                    include(\"missing.inc\");\ntest(a: 1);";
+        cache.open(&path, code.to_string());
+        let messages: Vec<String> = cache
+            .diagnostics(&path)
+            .into_iter()
+            .map(|d| d.message)
+            .collect();
+        assert_eq!(
+            messages,
+            vec![
+                format!("unable to find 'Example.nasl' in {root}; did you mean 'example.inc' or 'example.nasl'?"),
+                format!("unable to find 'missing.inc' in {root}"),
+            ]
+        );
+    }
+
+    #[test]
+    fn completions() {
+        let root = format!("{}/../.test-data", env!("CARGO_MANIFEST_DIR"));
+        let mut cache = Cache::new(vec![root.clone()]);
+        let path = format!("{root}/unsaved.nasl");
+        let labels =
+            |c: Vec<Completion>| -> Vec<String> { c.into_iter().map(|c| c.label).collect() };
+        cache.open(&path, "include(\"exa".to_string());
+        // include names are served from the index
+        cache.indexed(true);
+        let includes = cache.completions(&path, 0, 12);
+        assert_eq!(labels(includes.clone()), vec!["example.inc"]);
+        assert_eq!(includes[0].kind, CompletionKind::Include);
+        assert_eq!(
+            includes[0].replace,
+            Some((Point { row: 0, column: 9 }, Point { row: 0, column: 12 }))
+        );
+        // a column within ö is cut to the preceding character
+        cache.open(&path, "include(\"ö".to_string());
+        let includes = cache.completions(&path, 0, 10);
+        assert_eq!(
+            includes[0].replace,
+            Some((Point { row: 0, column: 9 }, Point { row: 0, column: 9 }))
+        );
+        cache.open(&path, "# include(\"exa".to_string());
+        assert!(cache
+            .completions(&path, 0, 14)
+            .iter()
+            .all(|c| c.kind != CompletionKind::Include));
+        let code = "include(\"example.inc\");\nfunction f(p) {\n  l = 1;\n  \n}\ng = 2;\n";
+        cache.open(&path, code.to_string());
+        assert_eq!(
+            labels(cache.completions(&path, 3, 2)),
+            vec!["f", "p", "l", "g", "test", "a"]
+        );
+        assert_eq!(
+            labels(cache.completions(&path, 5, 0)),
+            vec!["f", "g", "test", "a"]
+        );
+    }
+
+    #[test]
+    fn builtin_arguments() {
+        let dir = tempfile::tempdir().unwrap();
+        let root = dir.path().to_path_buf();
+        let nasl = root.join("nasl");
+        std::fs::create_dir_all(&nasl).unwrap();
+        std::fs::write(
+            nasl.join("nasl_init.c"),
+            r#"static init_func libfuncs[] = { {"http_get", http_get} };"#,
+        )
+        .unwrap();
+        std::fs::write(
+            nasl.join("nasl_http.c"),
+            "tree_cell *\nhttp_get (lex_ctxt *lexic)\n{\n  char *item = get_str_var_by_name (lexic, \"item\");\n  int port = get_int_var_by_name (lexic, \"port\", -1);\n}\n",
+        )
+        .unwrap();
+        let mut cache = Cache::new(vec![]);
+        cache.set_internal(root.to_str().unwrap());
+        let path = "/tmp/builtin-arguments.nasl";
+        cache.open(path, "http_get(item: 1, ".to_string());
+        let completion = cache.completions(path, 0, 18).into_iter().next().unwrap();
+        assert_eq!(completion.label, "port:");
+        assert_eq!(completion.kind, CompletionKind::Argument);
+        let (signature, call) = cache.signature(path, 0, 18).unwrap();
+        assert_eq!(signature.label(), "http_get(item:, port:)");
+        assert_eq!(signature.active(&call), None);
+        let (signature, call) = cache.signature(path, 0, 15).unwrap();
+        assert_eq!(signature.active(&call), Some(0));
+    }
+
+    #[test]
+    fn function_signatures() {
+        let dir = tempfile::tempdir().unwrap();
+        let root = dir.path().to_path_buf();
+        std::fs::write(
+            root.join("http.inc"),
+            "# sends a request\nfunction send(port, data) {\n  return data;\n}\n",
+        )
+        .unwrap();
+        let root = root.to_str().unwrap().to_string();
+        let mut cache = Cache::new(vec![root.clone()]);
+        let path = format!("{root}/test.nasl");
+        cache.open(
+            &path,
+            "include(\"http.inc\");\nsend(data: \"a\", port: 80);".to_string(),
+        );
+        let (signature, call) = cache.signature(&path, 1, 24).unwrap();
+        assert_eq!(signature.label(), "send(port:, data:)");
+        assert_eq!(signature.documentation, Some("sends a request".to_string()));
+        assert_eq!(signature.active(&call), Some(0));
+        let (signature, call) = cache.signature(&path, 1, 13).unwrap();
+        assert_eq!(signature.active(&call), Some(1));
+    }
+
+    #[test]
+    fn arguments() {
+        let dir = tempfile::tempdir().unwrap();
+        let root = dir.path().to_path_buf();
+        std::fs::write(
+            root.join("http.inc"),
+            "function send(port, data) {\n  if (isnull(data)) data = \"\";\n  return port + data;\n}\n",
+        )
+        .unwrap();
+        let root = root.to_str().unwrap().to_string();
+        let mut cache = Cache::new(vec![root.clone()]);
+        let path = format!("{root}/test.nasl");
+        let code = "include(\"http.inc\");\nsend(port: 1);\nif (1) {\n  send(dta: 2);\n}\n";
+        cache.open(&path, code.to_string());
+        let messages: Vec<String> = cache
+            .diagnostics(&path)
+            .into_iter()
+            .map(|d| d.message)
+            .collect();
+        assert_eq!(
+            messages,
+            vec![
+                "unknown argument 'dta' for send; did you mean 'data'?".to_string(),
+                "missing argument 'port' for send".to_string(),
+            ]
+        );
+    }
+
+    #[test]
+    fn undefined_functions() {
+        let dir = tempfile::tempdir().unwrap();
+        let root = dir.path().to_path_buf();
+        let nasl = root.join("openvas/nasl");
+        let feed = root.join("feed");
+        std::fs::create_dir_all(&nasl).unwrap();
+        std::fs::create_dir_all(&feed).unwrap();
+        std::fs::write(
+            nasl.join("nasl_init.c"),
+            r#"static init_func libfuncs[] = { {"display", nasl_display} };"#,
+        )
+        .unwrap();
+        std::fs::write(
+            feed.join("http.inc"),
+            "function send(port) {\n  display(port);\n}\n",
+        )
+        .unwrap();
+        std::fs::write(feed.join("other.inc"), "# send is not defined here\n").unwrap();
+        let feed = feed.to_str().unwrap().to_string();
+        let mut cache = Cache::new(vec![feed.clone()]);
+        cache.set_internal(root.join("openvas").to_str().unwrap());
+        let path = format!("{feed}/test.nasl");
+        let code = "if (description) {\n  exit(0);\n}\ndisplay(1);\nsend(port: 1);\n";
+        cache.open(&path, code.to_string());
+        let messages: Vec<String> = cache
+            .diagnostics(&path)
+            .into_iter()
+            .map(|d| d.message)
+            .collect();
+        assert_eq!(
+            messages,
+            vec!["undefined function 'exit'", "undefined function 'send'"]
+        );
+        // the defining includes are looked up within the index
+        cache.indexed(true);
+        let actions = cache.code_actions(
+            &path,
+            Point { row: 4, column: 0 },
+            Point { row: 4, column: 0 },
+        );
+        assert_eq!(actions.len(), 1);
+        assert_eq!(actions[0].title, "Add include(\"http.inc\")");
+        assert_eq!(
+            actions[0].edits,
+            vec![Edit {
+                start: Point { row: 3, column: 0 },
+                end: Point { row: 3, column: 0 },
+                text: "include(\"http.inc\");\n".to_string(),
+            }]
+        );
+        cache.open(&path, format!("include(\"other.inc\");\n{code}"));
+        let actions = cache.code_actions(
+            &path,
+            Point { row: 5, column: 0 },
+            Point { row: 5, column: 4 },
+        );
+        assert_eq!(actions[0].edits[0].start, Point { row: 1, column: 0 });
+        // without a call within the range nothing is looked up
+        let actions = cache.code_actions(
+            &path,
+            Point { row: 3, column: 0 },
+            Point { row: 3, column: 1 },
+        );
+        assert!(actions.is_empty());
+        // includes use the functions of the other includes of their includers
+        let path = format!("{feed}/uses.inc");
+        cache.open(&path, "function uses() {\n  helper();\n}\n".to_string());
+        assert!(cache.diagnostics(&path).is_empty());
+        let actions = cache.code_actions(
+            &path,
+            Point { row: 1, column: 2 },
+            Point { row: 1, column: 2 },
+        );
+        assert!(actions.is_empty());
+    }
+
+    #[test]
+    fn circular_includes() {
+        let dir = tempfile::tempdir().unwrap();
+        let root = dir.path().to_path_buf();
+        std::fs::write(root.join("a.inc"), "include(\"b.inc\");\nfunction a() {}").unwrap();
+        std::fs::write(root.join("b.inc"), "include(\"a.inc\");").unwrap();
+        let root = root.to_str().unwrap().to_string();
+        let mut cache = Cache::new(vec![root.clone()]);
+        let path = format!("{root}/test.nasl");
+        cache.open(&path, "include(\"a.inc\");\na();".to_string());
+        let messages: Vec<String> = cache
+            .diagnostics(&path)
+            .into_iter()
+            .map(|d| d.message)
+            .collect();
+        assert_eq!(
+            messages,
+            vec!["circular include: test.nasl -> a.inc -> b.inc -> a.inc".to_string()]
+        );
+    }
+
+    #[test]
+    fn undefined_variables() {
+        let dir = tempfile::tempdir().unwrap();
+        let root = dir.path().to_path_buf();
+        let nasl = root.join("openvas/nasl");
+        let feed = root.join("feed");
+        std::fs::create_dir_all(&nasl).unwrap();
+        std::fs::create_dir_all(&feed).unwrap();
+        std::fs::write(
+            nasl.join("nasl_init.c"),
+            r#"static init_func libfuncs[] = { {"display", nasl_display} };
+static init_nasl_ivars libivars[] = { {"TRUE", 1} };"#,
+        )
+        .unwrap();
+        std::fs::write(
+            feed.join("http.inc"),
+            "global_var HTTP_PORT;
+timeout = 5;
+",
+        )
+        .unwrap();
+        let feed = feed.to_str().unwrap().to_string();
+        let mut cache = Cache::new(vec![feed.clone()]);
+        cache.set_internal(root.join("openvas").to_str().unwrap());
+        let path = format!("{feed}/test.nasl");
+        let code = r#"include("http.inc");
+port = 80;
+display(prot, HTTP_PORT, timeout, TRUE);
+display(later);
+later = 1;
+function f(a) {
+  display(a, later, b);
+}
+"#;
+        cache.open(&path, code.to_string());
+        let messages: Vec<String> = cache
+            .diagnostics(&path)
+            .into_iter()
+            .map(|d| d.message)
+            .collect();
+        assert_eq!(
+            messages,
+            vec![
+                "undefined variable 'prot'; did you mean 'port'?",
+                "variable 'later' is used before it is assigned",
+                "undefined variable 'b'; did you mean 'a'?",
+            ]
+        );
+        // includes use the variables of their includers
+        let path = format!("{feed}/uses.inc");
+        cache.open(
+            &path,
+            "function uses() {\n  return HELPER_PORT;\n}\n".to_string(),
+        );
+        assert!(cache.diagnostics(&path).is_empty());
+    }
+
+    #[test]
+    fn unused_variables() {
+        let mut cache = Cache::new(vec![]);
+        let path = "/tmp/nasl-cache-unused-variables.nasl";
+        let code = "function f(a, b) {\n  c = 1;\n  return a;\n}\n\
+                    function g() {\n  local_var h;\n  h = 1;\n  h = 2;\n}\n";
+        cache.open(path, code.to_string());
+        let messages: Vec<String> = cache
+            .diagnostics(path)
+            .into_iter()
+            .map(|d| d.message)
+            .collect();
+        assert_eq!(
+            messages,
+            vec![
+                "unused parameter 'b'",
+                "unused variable 'c'",
+                "unused variable 'h'"
+            ]
+        );
+        let actions = cache.code_actions(
+            path,
+            Point { row: 1, column: 2 },
+            Point { row: 1, column: 2 },
+        );
+        assert_eq!(actions.len(), 1);
+        assert_eq!(actions[0].title, "Remove unused assignment to 'c'");
+        assert_eq!(
+            actions[0].edits,
+            vec![Edit {
+                start: Point { row: 1, column: 0 },
+                end: Point { row: 2, column: 0 },
+                text: String::new(),
+            }]
+        );
+        // a declaration is removed together with each dead write
+        let actions = cache.code_actions(
+            path,
+            Point { row: 7, column: 2 },
+            Point { row: 7, column: 2 },
+        );
+        assert_eq!(actions.len(), 1);
+        assert_eq!(actions[0].title, "Remove unused variable 'h'");
+        assert_eq!(actions[0].diagnostic.start, Point { row: 5, column: 12 });
+        let rows: Vec<(usize, usize)> = actions[0]
+            .edits
+            .iter()
+            .map(|e| (e.start.row, e.end.row))
+            .collect();
+        assert_eq!(rows, vec![(5, 6), (6, 7), (7, 8)]);
+    }
+
+    #[test]
+    fn unused_include_globals() {
+        let dir = tempfile::tempdir().unwrap();
+        let root = dir.path().to_path_buf();
+        std::fs::write(root.join("globals.inc"), "global_var G;\n").unwrap();
+        let root = root.to_str().unwrap().to_string();
+        let mut cache = Cache::new(vec![root.clone()]);
+        let path = format!("{root}/test.nasl");
+        let code = "include(\"globals.inc\");\nfunction f() {\n  G = 2;\n  l = 3;\n}\nf();\n";
+        cache.open(&path, code.to_string());
+        let messages: Vec<String> = cache
+            .diagnostics(&path)
+            .into_iter()
+            .map(|d| d.message)
+            .filter(|m| m.starts_with("unused variable"))
+            .collect();
+        assert_eq!(messages, vec!["unused variable 'l'"]);
+        let actions = cache.code_actions(
+            &path,
+            Point { row: 2, column: 2 },
+            Point { row: 2, column: 2 },
+        );
+        assert!(actions.is_empty());
+    }
+
+    #[test]
+    fn unused_includes() {
+        let dir = tempfile::tempdir().unwrap();
+        let root = dir.path().to_path_buf();
+        let files = [
+            (
+                "http.inc",
+                "include(\"misc.inc\");\nfunction http_get() {}\n",
+            ),
+            ("misc.inc", "global_var TIMEOUT;\nfunction misc() {}\n"),
+            ("keepalive.inc", "function keepalive() {\n  misc();\n}\n"),
+            ("unused.inc", "function unused() {}\n"),
+        ];
+        for (name, code) in files {
+            std::fs::write(root.join(name), code).unwrap();
+        }
+        let root = root.to_str().unwrap().to_string();
+        let mut cache = Cache::new(vec![root.clone()]);
+        let path = format!("{root}/test.nasl");
+        // misc.inc is used by keepalive.inc; http.inc includes it as well but comes later
+        let code = r#"include("misc.inc");
+include("unused.inc");
+include("http.inc");
+include("keepalive.inc");
+keepalive();
+"#;
+        cache.open(&path, code.to_string());
+        let messages: Vec<String> = cache
+            .diagnostics(&path)
+            .into_iter()
+            .map(|d| d.message)
+            .collect();
+        assert_eq!(
+            messages,
+            vec!["unused include 'unused.inc'", "unused include 'http.inc'"]
+        );
+        let actions = cache.code_actions(
+            &path,
+            Point { row: 1, column: 10 },
+            Point { row: 1, column: 10 },
+        );
+        assert_eq!(actions.len(), 1);
+        assert_eq!(actions[0].title, "Remove include(\"unused.inc\")");
+        assert_eq!(
+            actions[0].edits,
+            vec![Edit {
+                start: Point { row: 1, column: 0 },
+                end: Point { row: 2, column: 0 },
+                text: String::new(),
+            }]
+        );
+    }
+
+    #[test]
+    fn nested_calls_use_includes() {
+        let dir = tempfile::tempdir().unwrap();
+        let root = dir.path().to_path_buf();
+        std::fs::write(root.join("http.inc"), "function http_get(item) {}\n").unwrap();
+        std::fs::write(root.join("misc.inc"), "function misc() {}\n").unwrap();
+        let root = root.to_str().unwrap().to_string();
+        let mut cache = Cache::new(vec![root.clone()]);
+        let path = format!("{root}/test.nasl");
+        let code = r#"include("http.inc");
+include("misc.inc");
+if (http_get(item: "/")) {
+  a = misc();
+}
+"#;
+        cache.open(&path, code.to_string());
+        let messages: Vec<String> = cache
+            .diagnostics(&path)
+            .into_iter()
+            .map(|d| d.message)
+            .collect();
+        assert!(messages.is_empty(), "{messages:?}");
+    }
+
+    #[test]
+    fn workspace_symbols() {
+        let dir = tempfile::tempdir().unwrap();
+        let root = dir.path().to_path_buf();
+        std::fs::write(root.join("misc.inc"), "function misc_func() {}\n").unwrap();
+        let root = root.to_str().unwrap().to_string();
+        let mut cache = Cache::new(vec![root.clone()]);
+        let names = |cache: &mut Cache| -> Vec<String> {
+            cache
+                .workspace_symbols("misc")
+                .iter()
+                .map(|s| s.name().to_string())
+                .collect()
+        };
+        cache.build_index();
+        // a running build is cancelled by the next one
+        cache.build_index();
+        cache.indexed(true);
+        assert_eq!(names(&mut cache), vec!["misc_func"]);
+        // known paths neither get duplicated nor build the index again
+        cache.update_paths(vec![root.clone()]);
+        assert_eq!(cache.paths, vec![root.clone()]);
+        assert!(cache.index.is_some() && cache.building.is_none());
+        // opened documents are indexed again
+        let path = format!("{root}/misc.inc");
+        cache.open(&path, "function misc_renamed() {}\n".to_string());
+        assert_eq!(names(&mut cache), vec!["misc_renamed"]);
+        cache.close(&path);
+        assert_eq!(names(&mut cache), vec!["misc_func"]);
+        // files created or deleted on disk are indexed once they are outdated
+        let created = format!("{root}/misc_new.inc");
+        std::fs::write(&created, "function misc_new() {}\n").unwrap();
+        cache.outdate(&created);
+        assert_eq!(names(&mut cache), vec!["misc_new", "misc_func"]);
+        std::fs::remove_file(&created).unwrap();
+        cache.outdate(&created);
+        assert_eq!(names(&mut cache), vec!["misc_func"]);
+    }
 }
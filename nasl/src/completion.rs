@@ -0,0 +1,70 @@
+use tree_sitter::Point;
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum CompletionKind {
+    Builtin,
+    Function,
+    Variable,
+    Include,
+    Argument,
+}
+
+// Completion is a proposal for the identifier or include name at the cursor.
+// When replace is set the text between both points is replaced by label.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Completion {
+    pub label: String,
+    pub kind: CompletionKind,
+    pub detail: Option<String>,
+    pub replace: Option<(Point, Point)>,
+}
+
+// include_prefix returns the already typed name when line ends within the string literal of an
+// include call. Strings and comments mentioning include are skipped.
+pub fn include_prefix(line: &str) -> Option<&str> {
+    // quote is the quote and the start of the content of the string line ends in
+    let mut quote: Option<(char, usize)> = None;
+    let mut escaped = false;
+    for (i, c) in line.char_indices() {
+        match quote {
+            // only single quoted strings know escape sequences
+            Some(('\'', _)) if escaped => escaped = false,
+            Some(('\'', _)) if c == '\\' => escaped = true,
+            Some((q, _)) if c == q => quote = None,
+            Some(_) => {}
+            None if c == '#' => return None,
+            None if c == '"' || c == '\'' => quote = Some((c, i + 1)),
+            None => {}
+        }
+    }
+    let (_, start) = quote?;
+    let before = line[..start - 1]
+        .trim_end()
+        .strip_suffix('(')?
+        .trim_end()
+        .strip_suffix("include")?;
+    if before.ends_with(|c: char| c.is_alphanumeric() || c == '_') {
+        return None;
+    }
+    Some(&line[start..])
+}
+
+#[cfg(test)]
+mod tests {
+    use super::include_prefix;
+
+    #[test]
+    fn include_prefixes() {
+        assert_eq!(include_prefix("include(\"exa"), Some("exa"));
+        assert_eq!(include_prefix("  include( 'sub/"), Some("sub/"));
+        assert_eq!(include_prefix("include(\""), Some(""));
+        assert_eq!(include_prefix("include(\"example.inc\");"), None);
+        assert_eq!(include_prefix("include(name"), None);
+        assert_eq!(include_prefix("a = 1;"), None);
+        assert_eq!(include_prefix("# include(\"exa"), None);
+        assert_eq!(include_prefix("a = \"include(\"; b = \"exa"), None);
+        assert_eq!(include_prefix("myinclude(\"exa"), None);
+        assert_eq!(include_prefix("a = 'it\\'s'; include(\"exa"), Some("exa"));
+        assert_eq!(include_prefix("include(\"jörg"), Some("jörg"));
+    }
+}
@@ -0,0 +1,346 @@
+use tree_sitter::{Node, Point};
+
+use crate::{
+    signature::Signature,
+    types::{Argument, Identifier},
+};
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum Severity {
+    Error,
+    Warning,
+    Information,
+    Hint,
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Diagnostic {
+    pub start: Point,
+    pub end: Point,
+    pub severity: Severity,
+    pub message: String,
+}
+
+// unresolved_include creates an error for an include that is not found within searched.
+// similar contains include names that exist and are likely meant instead.
+pub fn unresolved_include(
+    include: &Identifier,
+    searched: &[&str],
+    similar: &[String],
+) -> Diagnostic {
+    let name = include.identifier.clone().unwrap_or_default();
+    let mut message = format!("unable to find '{name}' in {}", searched.join(", "));
+    if !similar.is_empty() {
+        let similar: Vec<String> = similar.iter().map(|s| format!("'{s}'")).collect();
+        message.push_str(&format!("; did you mean {}?", similar.join(" or ")));
+    }
+    Diagnostic {
+        start: include.start,
+        end: include.end,
+        severity: Severity::Error,
+        message,
+    }
+}
+
+// circular_include creates an error for an include that leads back to an already included file
+pub fn circular_include(include: &Identifier, chain: &[String]) -> Diagnostic {
+    Diagnostic {
+        start: include.start,
+        end: include.end,
+        severity: Severity::Error,
+        message: format!("circular include: {}", chain.join(" -> ")),
+    }
+}
+
+// MAX_DISTANCE is the maximal edit distance of an argument name to a parameter that is proposed
+// instead
+const MAX_DISTANCE: usize = 2;
+
+// distance returns the Levenshtein distance between a and b
+fn distance(a: &str, b: &str) -> usize {
+    let b: Vec<char> = b.chars().collect();
+    let mut previous: Vec<usize> = (0..=b.len()).collect();
+    for (i, ca) in a.chars().enumerate() {
+        let mut current = vec![i + 1];
+        for (j, cb) in b.iter().enumerate() {
+            let substitution = previous[j] + usize::from(ca != *cb);
+            current.push(substitution.min(previous[j + 1] + 1).min(current[j] + 1));
+        }
+        previous = current;
+    }
+    previous[b.len()]
+}
+
+// argument_diagnostics creates a warning for each named argument of a call that is not a named
+// parameter of signature and one for the required parameters that are not set.
+// Unknown arguments are only reported when the parameters of signature are complete.
+pub fn argument_diagnostics(
+    call: &Identifier,
+    arguments: &[Argument],
+    signature: &Signature,
+    complete: bool,
+) -> Vec<Diagnostic> {
+    let named: Vec<&Identifier> = arguments
+        .iter()
+        .filter_map(|a| match a {
+            Argument::Named(id) => Some(id),
+            _ => None,
+        })
+        .collect();
+    let is_set = |name: &str| named.iter().any(|n| n.matches(name));
+    let parameters: Vec<&str> = signature
+        .parameters
+        .iter()
+        .filter(|p| p.named)
+        .map(|p| p.name.as_str())
+        .collect();
+    let mut result: Vec<Diagnostic> = named
+        .iter()
+        .filter(|n| complete && !parameters.iter().any(|p| n.matches(p)))
+        .map(|n| {
+            let name = n.identifier.clone().unwrap_or_default();
+            let mut message = format!("unknown argument '{name}' for {}", signature.name);
+            let similar = parameters
+                .iter()
+                .filter(|p| !is_set(p))
+                .map(|p| (distance(&name, p), p))
+                .filter(|(d, _)| *d <= MAX_DISTANCE)
+                .min();
+            if let Some((_, p)) = similar {
+                message.push_str(&format!("; did you mean '{p}'?"));
+            }
+            Diagnostic {
+                start: n.start,
+                end: n.end,
+                severity: Severity::Warning,
+                message,
+            }
+        })
+        .collect();
+    let missing: Vec<String> = signature
+        .parameters
+        .iter()
+        .filter(|p| p.named && p.required && !is_set(&p.name))
+        .map(|p| format!("'{}'", p.name))
+        .collect();
+    if !missing.is_empty() {
+        let plural = if missing.len() > 1 { "s" } else { "" };
+        result.push(Diagnostic {
+            start: call.start,
+            end: call.end,
+            severity: Severity::Warning,
+            message: format!(
+                "missing argument{plural} {} for {}",
+                missing.join(", "),
+                signature.name
+            ),
+        });
+    }
+    result
+}
+
+// undefined_function creates a warning for a call of a function that is neither defined nor a
+// builtin
+pub fn undefined_function(call: &Identifier) -> Diagnostic {
+    Diagnostic {
+        start: call.start,
+        end: call.end,
+        severity: Severity::Warning,
+        message: format!(
+            "undefined function '{}'",
+            call.identifier.clone().unwrap_or_default()
+        ),
+    }
+}
+
+// undefined_variable creates a warning for a read of a variable that is not assigned before.
+// When the variable is assigned later on the warning says so, otherwise the most similar of
+// names is proposed.
+pub fn undefined_variable(
+    variable: &Identifier,
+    assigned_later: bool,
+    names: &[&str],
+) -> Diagnostic {
+    let name = variable.identifier.clone().unwrap_or_default();
+    let message = if assigned_later {
+        format!("variable '{name}' is used before it is assigned")
+    } else {
+        let mut message = format!("undefined variable '{name}'");
+        let similar = names
+            .iter()
+            .filter(|n| **n != name)
+            .map(|n| (distance(&name, n), n))
+            .filter(|(d, _)| *d <= MAX_DISTANCE)
+            .min();
+        if let Some((_, n)) = similar {
+            message.push_str(&format!("; did you mean '{n}'?"));
+        }
+        message
+    };
+    Diagnostic {
+        start: variable.start,
+        end: variable.end,
+        severity: Severity::Warning,
+        message,
+    }
+}
+
+// unused_variable creates a warning for an assignment to a local variable that is never read
+pub fn unused_variable(variable: &Identifier) -> Diagnostic {
+    Diagnostic {
+        start: variable.start,
+        end: variable.end,
+        severity: Severity::Warning,
+        message: format!(
+            "unused variable '{}'",
+            variable.identifier.clone().unwrap_or_default()
+        ),
+    }
+}
+
+// unused_parameter creates a warning for a parameter that is never read within its function
+pub fn unused_parameter(parameter: &Identifier) -> Diagnostic {
+    Diagnostic {
+        start: parameter.start,
+        end: parameter.end,
+        severity: Severity::Warning,
+        message: format!(
+            "unused parameter '{}'",
+            parameter.identifier.clone().unwrap_or_default()
+        ),
+    }
+}
+
+// unused_include creates a warning for an include that defines nothing used by the script
+pub fn unused_include(include: &Identifier) -> Diagnostic {
+    Diagnostic {
+        start: include.start,
+        end: include.end,
+        severity: Severity::Warning,
+        message: format!(
+            "unused include '{}'",
+            include.identifier.clone().unwrap_or_default()
+        ),
+    }
+}
+
+// MAX_TOKEN_LENGTH is the maximal length of unexpected code that is shown within a message
+const MAX_TOKEN_LENGTH: usize = 20;
+
+fn unexpected(code: &str, node: &Node<'_>) -> String {
+    let text = code[node.byte_range()].trim();
+    if text.is_empty() || text.len() > MAX_TOKEN_LENGTH || text.contains('\n') {
+        "unexpected token".to_string()
+    } else {
+        format!("unexpected '{text}'")
+    }
+}
+
+// syntax_errors returns a diagnostic for each ERROR and MISSING node within node.
+// Children of an ERROR node are not reported separately.
+pub fn syntax_errors(code: &str, node: &Node<'_>) -> Vec<Diagnostic> {
+    if node.is_missing() {
+        return vec![Diagnostic {
+            start: node.start_position(),
+            end: node.end_position(),
+            severity: Severity::Error,
+            message: format!("missing '{}'", node.kind()),
+        }];
+    }
+    if node.is_error() {
+        return vec![Diagnostic {
+            start: node.start_position(),
+            end: node.end_position(),
+            severity: Severity::Error,
+            message: unexpected(code, node),
+        }];
+    }
+    if !node.has_error() {
+        return vec![];
+    }
+    let crsr = &mut node.walk();
+    node.children(crsr)
+        .flat_map(|c| syntax_errors(code, &c))
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use tree_sitter::Point;
+
+    use crate::{
+        interpret::nasl_tree,
+        signature::{Parameter, Signature},
+        types::{Argument, Identifier},
+    };
+
+    use super::{argument_diagnostics, syntax_errors, Severity};
+
+    #[test]
+    fn missing_and_unexpected() {
+        let code = "if (x) { y = 1; \nfoo(a:1 b:2);\n";
+        let tree = nasl_tree(code, None).unwrap();
+        let result = syntax_errors(code, &tree.root_node());
+        let messages: Vec<&str> = result.iter().map(|d| d.message.as_str()).collect();
+        assert_eq!(messages, vec!["unexpected 'b:2'", "missing '}'"]);
+        assert!(result.iter().all(|d| d.severity == Severity::Error));
+        assert_eq!(result[0].start, Point { row: 1, column: 8 });
+        assert_eq!(result[0].end, Point { row: 1, column: 11 });
+    }
+
+    #[test]
+    fn arguments() {
+        let parameter = |name: &str, required| Parameter {
+            name: name.to_string(),
+            named: true,
+            required,
+        };
+        let signature = Signature {
+            name: "http_get".to_string(),
+            parameters: vec![parameter("item", true), parameter("port", true)],
+            documentation: None,
+        };
+        let id = |name: &str, column| Identifier {
+            start: Point { row: 0, column },
+            end: Point {
+                row: 0,
+                column: column + name.len(),
+            },
+            identifier: Some(name.to_string()),
+        };
+        let result = argument_diagnostics(
+            &id("http_get", 0),
+            &[Argument::Named(id("itme", 9))],
+            &signature,
+            true,
+        );
+        let messages: Vec<&str> = result.iter().map(|d| d.message.as_str()).collect();
+        assert_eq!(
+            messages,
+            vec![
+                "unknown argument 'itme' for http_get; did you mean 'item'?",
+                "missing arguments 'item', 'port' for http_get",
+            ]
+        );
+        assert_eq!(result[0].start, Point { row: 0, column: 9 });
+        assert!(result.iter().all(|d| d.severity == Severity::Warning));
+        let result = argument_diagnostics(
+            &id("http_get", 0),
+            &[Argument::Named(id("itme", 9))],
+            &signature,
+            false,
+        );
+        assert_eq!(result.len(), 1);
+        assert_eq!(
+            result[0].message,
+            "missing arguments 'item', 'port' for http_get"
+        );
+    }
+
+    #[test]
+    fn valid() {
+        let code = "a = 1;\nfoo(a: a);\n";
+        let tree = nasl_tree(code, None).unwrap();
+        assert_eq!(syntax_errors(code, &tree.root_node()), vec![]);
+    }
+}
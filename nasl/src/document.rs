@@ -0,0 +1,86 @@
+use tracing::warn;
+use tree_sitter::{InputEdit, Point, Tree};
+
+use crate::interpret::nasl_tree;
+
+// Document contains the code of an opened document as well as the last parsed tree so that
+// changes can be parsed incrementally.
+#[derive(Clone, Debug)]
+pub struct Document {
+    pub code: String,
+    tree: Option<Tree>,
+}
+
+// to_point returns the row and byte column of offset within code
+fn to_point(code: &str, offset: usize) -> Point {
+    let before = &code[..offset];
+    let row = before.matches('\n').count();
+    let column = match before.rfind('\n') {
+        Some(i) => offset - i - 1,
+        None => offset,
+    };
+    Point { row, column }
+}
+
+impl Document {
+    pub fn new(code: String) -> Document {
+        let mut document = Document { code, tree: None };
+        document.reparse();
+        document
+    }
+
+    // edit replaces the bytes between start and end with text.
+    // The tree is marked as edited but is not parsed again until reparse is called.
+    pub fn edit(&mut self, start: usize, end: usize, text: &str) {
+        let start_position = to_point(&self.code, start);
+        let old_end_position = to_point(&self.code, end);
+        self.code.replace_range(start..end, text);
+        let new_end_byte = start + text.len();
+        if let Some(tree) = &mut self.tree {
+            tree.edit(&InputEdit {
+                start_byte: start,
+                old_end_byte: end,
+                new_end_byte,
+                start_position,
+                old_end_position,
+                new_end_position: to_point(&self.code, new_end_byte),
+            });
+        }
+    }
+
+    // reparse parses the code while reusing the unchanged parts of the previous tree
+    pub fn reparse(&mut self) {
+        self.tree = match nasl_tree(&self.code, self.tree.as_ref()) {
+            Ok(tree) => Some(tree),
+            Err(err) => {
+                warn!("unable to parse document: {err}");
+                None
+            }
+        };
+    }
+
+    pub fn tree(&self) -> Option<&Tree> {
+        self.tree.as_ref()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::interpret::nasl_tree;
+
+    use super::Document;
+
+    #[test]
+    fn incremental() {
+        let mut document = Document::new("a = 1;\nb = a;\n".to_string());
+        let start = document.code.find("b = a").unwrap() + 4;
+        document.edit(start, start + 1, "test(a)");
+        document.reparse();
+        assert_eq!(document.code, "a = 1;\nb = test(a);\n");
+        let expected = nasl_tree(&document.code, None).unwrap();
+        assert_eq!(
+            document.tree().map(|t| t.root_node().to_sexp()),
+            Some(expected.root_node().to_sexp())
+        );
+    }
+}
@@ -0,0 +1,368 @@
+use std::{
+    collections::HashMap,
+    path::Path,
+    sync::atomic::{AtomicBool, Ordering},
+    thread,
+};
+
+use tracing::{debug, warn};
+use walkdir::WalkDir;
+
+use crate::{
+    interpret::NASLInterpreter,
+    types::{Argument, Identifier},
+};
+
+// MAX_RESULTS is the maximal amount of symbols returned by a search
+pub const MAX_RESULTS: usize = 500;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum IndexKind {
+    // Function is a function defined within an .inc file
+    Function,
+    // Oid is the script_oid of a VT
+    Oid,
+    // Name is the script_name of a VT
+    Name,
+}
+
+// Indexed is a symbol of a file within the configured paths
+#[derive(Clone, Debug, PartialEq)]
+pub struct Indexed {
+    pub path: String,
+    pub id: Identifier,
+    pub kind: IndexKind,
+    // container is the name of the include of a function; for the oid of a VT it is its name
+    // and vice versa
+    pub container: Option<String>,
+}
+
+impl Indexed {
+    pub fn name(&self) -> &str {
+        self.id.identifier.as_deref().unwrap_or_default()
+    }
+}
+
+// File contains the indexed symbols and includes of a file
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct File {
+    pub symbols: Vec<Indexed>,
+    // includes contains the names used within the include calls of the file
+    pub includes: Vec<String>,
+}
+
+// index returns the functions of an .inc file or the oid and name of a .nasl file as well as
+// the includes of both
+pub fn index(path: &str, code: &str) -> File {
+    let script = path.ends_with(".nasl");
+    if !script && !path.ends_with(".inc") {
+        return File::default();
+    }
+    // avoid parsing files that cannot contain any symbol or include
+    let candidate = match script {
+        true => code.contains("script_oid") || code.contains("script_name"),
+        false => code.contains("function"),
+    };
+    if !candidate && !code.contains("include") {
+        return File::default();
+    }
+    let interpreter = match NASLInterpreter::new(path, code) {
+        Ok(i) => i,
+        Err(_) => return File::default(),
+    };
+    File {
+        symbols: match candidate {
+            true => symbols(path, script, &interpreter),
+            false => vec![],
+        },
+        includes: interpreter.includes().cloned().collect(),
+    }
+}
+
+// symbols returns the functions of an .inc file or the oid and name of a .nasl file
+fn symbols(path: &str, script: bool, interpreter: &NASLInterpreter) -> Vec<Indexed> {
+    let file = Path::new(path)
+        .file_name()
+        .and_then(|n| n.to_str())
+        .map(|n| n.to_string());
+    let symbol = |id: &Identifier, kind: IndexKind, container: Option<String>| Indexed {
+        path: path.to_string(),
+        id: id.clone(),
+        kind,
+        container,
+    };
+    if !script {
+        return interpreter
+            .scopes()
+            .iter()
+            .filter_map(|s| s.function.as_ref())
+            .map(|f| symbol(f, IndexKind::Function, file.clone()))
+            .collect();
+    }
+    let value = |name: &str| {
+        interpreter
+            .all_calls()
+            .filter(|(id, _)| id.matches(name))
+            .flat_map(|(_, arguments)| arguments)
+            .find_map(|a| match a {
+                Argument::StringLiteral(id) => Some(id.clone()),
+                _ => None,
+            })
+    };
+    let oid = value("script_oid");
+    let name = value("script_name");
+    let text = |id: &Option<Identifier>| id.as_ref().and_then(|i| i.identifier.clone());
+    let mut result = vec![];
+    if let Some(o) = &oid {
+        result.push(symbol(
+            o,
+            IndexKind::Oid,
+            text(&name).or_else(|| file.clone()),
+        ));
+    }
+    if let Some(n) = &name {
+        result.push(symbol(
+            n,
+            IndexKind::Name,
+            text(&oid).or_else(|| file.clone()),
+        ));
+    }
+    result
+}
+
+// Index contains each .nasl and .inc file within the configured paths with the functions of the
+// .inc files, the oids and names of the .nasl files and the includes of both
+#[derive(Debug, Default)]
+pub struct Index {
+    files: HashMap<String, File>,
+}
+
+impl Index {
+    // build indexes the files within paths; the files are split between a thread per cpu.
+    // Once cancelled is set the remaining files are skipped.
+    pub fn build(paths: &[&str], cancelled: &AtomicBool) -> Self {
+        let files: Vec<String> = paths
+            .iter()
+            .flat_map(|p| WalkDir::new(p).into_iter().filter_map(|e| e.ok()))
+            .take_while(|_| !cancelled.load(Ordering::Relaxed))
+            .filter(|e| e.file_type().is_file())
+            .filter_map(|e| e.path().to_str().map(|p| p.to_string()))
+            .filter(|p| p.ends_with(".nasl") || p.ends_with(".inc"))
+            .collect();
+        let size = files.len() / num_cpus::get() + 1;
+        let indexed: HashMap<String, File> = thread::scope(|s| {
+            let workers: Vec<_> = files
+                .chunks(size)
+                .map(|chunk| {
+                    s.spawn(move || {
+                        chunk
+                            .iter()
+                            .take_while(|_| !cancelled.load(Ordering::Relaxed))
+                            .filter_map(|p| match NASLInterpreter::read(p) {
+                                Ok(code) => Some((p.clone(), index(p, &code))),
+                                Err(_) => None,
+                            })
+                            .collect::<Vec<(String, File)>>()
+                    })
+                })
+                .collect();
+            workers
+                .into_iter()
+                .zip(files.chunks(size))
+                .flat_map(|(w, chunk)| match w.join() {
+                    Ok(indexed) => indexed,
+                    Err(_) => {
+                        warn!(
+                            "unable to index {} files starting with {}",
+                            chunk.len(),
+                            chunk[0]
+                        );
+                        vec![]
+                    }
+                })
+                .collect()
+        });
+        let index = Index { files: indexed };
+        debug!(
+            "indexed {} symbols of {} files",
+            index.symbols().count(),
+            files.len()
+        );
+        index
+    }
+
+    // update replaces the symbols and includes of path; without a file path is removed
+    pub fn update(&mut self, path: &str, file: Option<File>) {
+        match file {
+            Some(f) => self.files.insert(path.to_string(), f),
+            None => self.files.remove(path),
+        };
+    }
+
+    // includes returns the path of each indexed .inc file
+    pub fn includes(&self) -> impl Iterator<Item = &str> {
+        self.files
+            .keys()
+            .map(|p| p.as_str())
+            .filter(|p| p.ends_with(".inc"))
+    }
+
+    fn symbols(&self) -> impl Iterator<Item = &Indexed> {
+        self.files.values().flat_map(|f| &f.symbols)
+    }
+
+    // functions returns each indexed definition of the function name
+    pub fn functions<'a>(&'a self, name: &'a str) -> impl Iterator<Item = &'a Indexed> + 'a {
+        self.symbols()
+            .filter(move |s| s.kind == IndexKind::Function && s.name() == name)
+    }
+
+    // includers returns the path of each file that includes name directly
+    pub fn includers<'a>(&'a self, name: &'a str) -> impl Iterator<Item = &'a str> + 'a {
+        self.files
+            .iter()
+            .filter(move |(_, f)| f.includes.iter().any(|i| i == name))
+            .map(|(p, _)| p.as_str())
+    }
+
+    // search returns the symbols fuzzy matching query; the best matches first
+    pub fn search(&self, query: &str) -> Vec<&Indexed> {
+        let mut result: Vec<(i64, &Indexed)> = self
+            .symbols()
+            .filter_map(|s| Some((fuzzy_score(query, s.name())?, s)))
+            .collect();
+        result.sort_by(|(a, x), (b, y)| {
+            b.cmp(a)
+                .then_with(|| x.name().len().cmp(&y.name().len()))
+                .then_with(|| x.name().cmp(y.name()))
+                .then_with(|| x.path.cmp(&y.path))
+        });
+        result
+            .into_iter()
+            .take(MAX_RESULTS)
+            .map(|(_, s)| s)
+            .collect()
+    }
+}
+
+// fuzzy_score returns a score when candidate contains each character of query in order ignoring
+// case. Consecutive characters and characters starting a word score higher, skipped characters
+// lower.
+pub fn fuzzy_score(query: &str, candidate: &str) -> Option<i64> {
+    let candidate: Vec<char> = candidate.chars().collect();
+    let mut score = 0;
+    let mut next = 0;
+    for q in query.chars() {
+        let found =
+            (next..candidate.len()).find(|&i| candidate[i].to_lowercase().eq(q.to_lowercase()))?;
+        score += 1;
+        if found > 0 && found == next {
+            score += 5;
+        }
+        if found == 0 || !candidate[found - 1].is_alphanumeric() {
+            score += 3;
+        }
+        score -= (found - next) as i64;
+        next = found + 1;
+    }
+    Some(score)
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::atomic::AtomicBool;
+
+    use super::{fuzzy_score, Index, IndexKind};
+
+    #[test]
+    fn fuzzy() {
+        assert!(fuzzy_score("gbp", "get_http_banner").is_none());
+        assert!(fuzzy_score("ghb", "get_http_banner").is_some());
+        assert!(fuzzy_score("HTTP", "get_http_banner").is_some());
+        assert!(
+            fuzzy_score("banner", "get_banner").unwrap()
+                > fuzzy_score("banner", "b_a_n_n_e_r").unwrap()
+        );
+        assert_eq!(fuzzy_score("", "anything"), Some(0));
+    }
+
+    #[test]
+    fn search() {
+        let dir = tempfile::tempdir().unwrap();
+        let root = dir.path().to_path_buf();
+        std::fs::create_dir_all(root.join("2023")).unwrap();
+        std::fs::write(
+            root.join("http_func.inc"),
+            "function get_http_banner(port) {}\nfunction http_get(item, port) {}\n",
+        )
+        .unwrap();
+        std::fs::write(
+            root.join("2023/gb_example.nasl"),
+            "if (description) {\n  script_oid(\"1.3.6.1.4.1.25623.1.0.1\");\n  script_name(\"Example HTTP Detection\");\n}\n",
+        )
+        .unwrap();
+        std::fs::write(root.join("empty.inc"), "x = 1;\n").unwrap();
+        std::fs::write(
+            root.join("2023/gb_other.nasl"),
+            "include(\"http_func.inc\");\n",
+        )
+        .unwrap();
+        let mut index = Index::build(&[root.to_str().unwrap()], &AtomicBool::new(false));
+        assert_eq!(
+            Index::build(&[root.to_str().unwrap()], &AtomicBool::new(true))
+                .search("")
+                .len(),
+            0
+        );
+        let found: Vec<(&str, IndexKind, Option<String>)> = index
+            .search("httpget")
+            .into_iter()
+            .map(|s| (s.name(), s.kind, s.container.clone()))
+            .collect();
+        assert_eq!(
+            found,
+            vec![(
+                "http_get",
+                IndexKind::Function,
+                Some("http_func.inc".to_string())
+            )]
+        );
+        let names: Vec<&str> = index.search("http").into_iter().map(|s| s.name()).collect();
+        assert_eq!(
+            names,
+            vec!["http_get", "get_http_banner", "Example HTTP Detection"]
+        );
+        let found = index.search("25623.1.0.1");
+        assert_eq!(found.len(), 1);
+        assert_eq!(found[0].kind, IndexKind::Oid);
+        assert_eq!(
+            found[0].container,
+            Some("Example HTTP Detection".to_string())
+        );
+        assert_eq!(index.search("").len(), 4);
+        let paths: Vec<&str> = index
+            .functions("http_get")
+            .map(|s| s.path.as_str())
+            .collect();
+        assert_eq!(paths, vec![root.join("http_func.inc").to_str().unwrap()]);
+        assert_eq!(index.functions("Example HTTP Detection").count(), 0);
+        let includers: Vec<&str> = index.includers("http_func.inc").collect();
+        assert_eq!(
+            includers,
+            vec![root.join("2023/gb_other.nasl").to_str().unwrap()]
+        );
+        let mut includes: Vec<&str> = index.includes().collect();
+        includes.sort();
+        assert_eq!(
+            includes,
+            vec![
+                root.join("empty.inc").to_str().unwrap(),
+                root.join("http_func.inc").to_str().unwrap()
+            ]
+        );
+        let path = root.join("http_func.inc");
+        index.update(path.to_str().unwrap(), None);
+        assert!(index.search("httpget").is_empty());
+        assert_eq!(index.includes().count(), 1);
+    }
+}
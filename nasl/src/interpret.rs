@@ -1,22 +1,37 @@
-use std::{error, fmt::Display, fs, ops::Range, path::Path};
+use std::{collections::HashMap, error, fmt::Display, fs, ops::Range, path::Path, sync::Arc};
 use tracing::{trace, warn};
 use tree_sitter::{Language, Node, Point, Tree};
 
 use crate::{
-    lookup::{find_definitions, Lookup, Jumpable},
-    types::{to_pos, Argument, Identifier},
+    lookup::{
+        declared_local, find_definitions, nested_calls, visible_definitions, CodeContainer,
+        Jumpable, Lookup,
+    },
+    node_ext::{ReferenceExt, RequiredParameterExt},
+    symbols::{outline, Symbol},
+    types::{Argument, Definition, Identifier},
+    variables::{scopes, Scope, Usage},
 };
 
 #[derive(Debug, PartialEq, Clone)]
 pub struct SearchParameter<'a> {
     pub origin: &'a str,
     pub name: &'a str,
-    pub pos: f32,
+    pub pos: Point,
 }
 
+// Load is used to create the interpreter of a path
+pub type Load<'a> = dyn FnMut(&str) -> Result<NASLInterpreter, Box<dyn error::Error>> + 'a;
+
 #[derive(Clone, Debug)]
 pub struct NASLInterpreter {
-    lookup: Lookup,
+    // lookup and references are shared so that cached interpreters are cheap to clone
+    lookup: Arc<Lookup>,
+    references: Arc<Vec<Identifier>>,
+    // required contains the parameters each caller of a function is expected to set
+    required: Arc<HashMap<String, Vec<String>>>,
+    // scopes contains the variable usages of the top level code and of each function
+    scopes: Arc<Vec<Scope>>,
 }
 
 #[derive(Debug)]
@@ -47,65 +62,140 @@ pub fn nasl_tree(code: &str, previous: Option<&Tree>) -> Result<Tree, Error> {
     tree(tree_sitter_nasl::language(), code, previous)
 }
 
-fn find_identifier(pos: f32, code: &str, n: &Node<'_>) -> Option<Range<usize>> {
-    let nspos = to_pos(n.range().start_point.row, n.range().start_point.column);
-    let nepos = to_pos(n.range().end_point.row, n.range().end_point.column);
-    if pos >= nspos && pos <= nepos {
+fn find_identifier(pos: Point, n: &Node<'_>) -> Option<Range<usize>> {
+    if pos >= n.start_position() && pos <= n.end_position() {
         if n.child_count() == 0 && n.kind() == "identifier" {
             return Some(n.byte_range());
         }
         let crsr = &mut n.walk();
         let mut icidx = n
             .named_children(crsr)
-            .filter_map(|i| find_identifier(pos, code, &i));
+            .filter_map(|i| find_identifier(pos, &i));
         return icidx.next();
     }
     None
 }
 
 impl NASLInterpreter {
-    fn new(origin: &str, code: &str) -> Result<NASLInterpreter, Box<dyn error::Error>> {
+    pub fn new(origin: &str, code: &str) -> Result<NASLInterpreter, Box<dyn error::Error>> {
         let tree = nasl_tree(code, None)?;
+        Ok(NASLInterpreter::from_tree(origin, code, &tree))
+    }
+
+    // from_tree creates an interpreter based on an already parsed tree of code
+    pub fn from_tree(origin: &str, code: &str, tree: &Tree) -> NASLInterpreter {
         let node = &tree.root_node();
         let lookup = Lookup::new(origin, code, node);
+        let references = node.references(&CodeContainer::new(origin, code, None));
+        let crsr = &mut node.walk();
+        let required = node
+            .named_children(crsr)
+            .filter(|c| c.kind() == "function_definition")
+            .filter_map(|c| {
+                let name = c
+                    .child_by_field_name("declarator")
+                    .and_then(|d| d.child_by_field_name("declarator"))?;
+                Some((code[name.byte_range()].to_string(), c.required_parameters(code)))
+            })
+            .collect();
 
-        Ok(NASLInterpreter { lookup })
+        NASLInterpreter {
+            lookup: Arc::new(lookup),
+            references: Arc::new(references),
+            required: Arc::new(required),
+            scopes: Arc::new(scopes(code, node)),
+        }
     }
 
+    // new_with_includes returns the interpreter of path and of all of its includes.
+    // load is used to create the interpreter of each file. Each file is only loaded once even when
+    // it is included multiple times or circularly.
     pub fn new_with_includes(
         path: &str,
         paths: Vec<String>,
-        code: Option<&str>,
+        load: &mut Load<'_>,
     ) -> Result<Vec<NASLInterpreter>, Box<dyn error::Error>> {
-        let code = if let Some(code) = code {
-            code.to_string()
-        } else {
-            NASLInterpreter::read(path)?
-        };
-        let init = NASLInterpreter::new(path, &code)?;
-        let pths = paths.clone();
-        let incs: Vec<NASLInterpreter> = init
+        let mut result = vec![];
+        Self::walk_includes(path, &paths, load, &mut vec![], &mut result, &mut vec![])?;
+        Ok(result)
+    }
+
+    // include_cycles returns each include chain starting at path that ends in a file that is
+    // already being included.
+    pub fn include_cycles(
+        path: &str,
+        paths: Vec<String>,
+        load: &mut Load<'_>,
+    ) -> Result<Vec<Vec<String>>, Box<dyn error::Error>> {
+        let mut cycles = vec![];
+        Self::walk_includes(path, &paths, load, &mut vec![], &mut vec![], &mut cycles)?;
+        Ok(cycles)
+    }
+
+    // walk_includes loads path and its includes depth first into result.
+    // chain contains the paths that are currently being included.
+    fn walk_includes(
+        path: &str,
+        paths: &[String],
+        load: &mut Load<'_>,
+        chain: &mut Vec<String>,
+        result: &mut Vec<NASLInterpreter>,
+        cycles: &mut Vec<Vec<String>>,
+    ) -> Result<(), Box<dyn error::Error>> {
+        let init = load(path)?;
+        let incs: Vec<String> = init
             .includes()
-            .flat_map(|i| pths.iter().map(|p| format!("{p}/{}", i.clone())))
+            .flat_map(|i| paths.iter().map(move |p| format!("{p}/{i}")))
             .map(|p| p.strip_prefix("file://").unwrap_or(&p).to_string())
             .filter(|p| Path::new(p).exists())
-            .flat_map(|p| {
-                trace!("parsing {p}");
-                Self::new_with_includes(&p, paths.clone(), None)
-            })
-            .flatten()
             .collect();
-        let mut result = vec![init];
-        result.extend(incs);
-        Ok(result)
+        chain.push(path.to_string());
+        result.push(init);
+        for p in incs {
+            if chain.contains(&p) {
+                let mut cycle = chain.clone();
+                cycle.push(p);
+                cycles.push(cycle);
+                continue;
+            }
+            if result.iter().any(|i| i.lookup.origin == p) {
+                continue;
+            }
+            trace!("parsing {p}");
+            if let Err(err) = Self::walk_includes(&p, paths, load, chain, result, cycles) {
+                trace!("unable to load {p}: {err}");
+            }
+        }
+        chain.pop();
+        Ok(())
     }
 
+    // leading_comment returns the text of the `#` comment lines directly above row
+    pub fn leading_comment(code: &str, row: usize) -> Option<String> {
+        let lines: Vec<&str> = code.lines().take(row).collect();
+        let comment: Vec<&str> = lines
+            .iter()
+            .rev()
+            .map(|l| l.trim())
+            .take_while(|l| l.starts_with('#'))
+            .map(|l| l.trim_start_matches('#').trim())
+            .collect();
+        if comment.is_empty() {
+            return None;
+        }
+        Some(comment.into_iter().rev().collect::<Vec<&str>>().join("\n"))
+    }
+
+    // read returns the content of path decoded as UTF-8 or, when it is no valid UTF-8, as Latin-1
     pub fn read(path: &str) -> Result<String, std::io::Error> {
-        fs::read(path).map(|bs| bs.iter().map(|&b| b as char).collect())
+        fs::read(path).map(|bs| {
+            String::from_utf8(bs)
+                .unwrap_or_else(|err| err.into_bytes().iter().map(|&b| b as char).collect())
+        })
     }
 
     pub fn origin(self) -> String {
-        self.lookup.origin
+        self.lookup.origin.clone()
     }
 
     pub fn search_parameter<'a>(
@@ -114,17 +204,8 @@ impl NASLInterpreter {
         line: usize,
         column: usize,
     ) -> Option<SearchParameter<'a>> {
-        let pos = to_pos(line, column);
         match nasl_tree(code, None) {
-            Ok(tree) => {
-                return find_identifier(pos, code, &tree.root_node().clone()).map(|name| {
-                    SearchParameter {
-                        origin,
-                        name: &code[name],
-                        pos,
-                    }
-                });
-            }
+            Ok(tree) => Self::search_parameter_in_tree(origin, code, &tree, line, column),
             Err(err) => {
                 warn!("unable to parse {origin}: {err}");
                 None
@@ -132,7 +213,23 @@ impl NASLInterpreter {
         }
     }
 
-    pub fn includes<'a>(&'a self) -> impl Iterator<Item = &String> + 'a {
+    // search_parameter_in_tree returns the identifier at line and column of an already parsed tree
+    pub fn search_parameter_in_tree<'a>(
+        origin: &'a str,
+        code: &'a str,
+        tree: &Tree,
+        line: usize,
+        column: usize,
+    ) -> Option<SearchParameter<'a>> {
+        let pos = Point { row: line, column };
+        find_identifier(pos, &tree.root_node()).map(|name| SearchParameter {
+            origin,
+            name: &code[name],
+            pos,
+        })
+    }
+
+    pub fn includes<'a>(&'a self) -> impl Iterator<Item = &'a String> + 'a {
         self.lookup.includes.iter()
     }
 
@@ -150,20 +247,98 @@ impl NASLInterpreter {
             _ => None,
         })
     }
+
+    // all_calls returns each call expression including the ones within blocks
+    pub fn all_calls(&self) -> impl Iterator<Item = (&Identifier, &Vec<Argument>)> + '_ {
+        nested_calls(&self.lookup).filter_map(|j| match j {
+            Jumpable::CallExpression(id, params) => Some((id, params)),
+            _ => None,
+        })
+    }
+
+    pub fn scopes(&self) -> &[Scope] {
+        &self.scopes
+    }
+
+    // functions returns the name of each function defined within the file
+    pub fn functions(&self) -> impl Iterator<Item = &str> + '_ {
+        self.scopes
+            .iter()
+            .filter_map(|s| s.function.as_ref()?.identifier.as_deref())
+    }
+
+    // used_names returns the name of each called function and of each read variable
+    pub fn used_names(&self) -> impl Iterator<Item = &str> + '_ {
+        self.all_calls()
+            .filter_map(|(id, _)| id.identifier.as_deref())
+            .chain(
+                self.scopes
+                    .iter()
+                    .flat_map(|s| s.variables.iter())
+                    .filter(|v| v.usage == Usage::Read)
+                    .filter_map(|v| v.id.identifier.as_deref()),
+            )
+    }
+
+    // globals returns the variables of the top level code as well as the variables declared by
+    // global_var
+    pub fn globals(&self) -> impl Iterator<Item = &str> + '_ {
+        self.scopes
+            .iter()
+            .filter(|s| s.function.is_none())
+            .flat_map(|s| s.writes())
+            .chain(self.scopes.iter().flat_map(|s| s.globals()))
+    }
+
+    // symbols returns the outline of the file
+    pub fn symbols(&self) -> Vec<Symbol> {
+        outline(&self.lookup, &self.scopes)
+    }
+
+    // required_parameters returns the parameters of the function name that each caller has to set
+    pub fn required_parameters(&self, name: &str) -> Vec<String> {
+        self.required.get(name).cloned().unwrap_or_default()
+    }
+
     pub fn find_points<'a>(&'a self, sp: &'a SearchParameter) -> impl Iterator<Item = Point> + 'a {
         find_definitions(&self.lookup.definitions, &self.lookup.origin, sp)
             .map(|i| i.start)
     }
+
+    // declares_local returns true when sp is within a block that declares its name by local_var.
+    // Definitions of other files are not visible then.
+    pub fn declares_local(&self, sp: &SearchParameter) -> bool {
+        declared_local(&self.lookup.definitions, &self.lookup.origin, sp)
+    }
+
+    // visible_definitions returns the definitions visible at pos or, without a position, the
+    // definitions of the outer scope
+    pub fn visible_definitions(&self, pos: Option<Point>) -> Vec<Definition> {
+        visible_definitions(&self.lookup.definitions, pos)
+    }
+
+    // function returns the definition and parameters of the function name
+    pub fn function(&self, name: &str) -> Option<(Identifier, Vec<Identifier>)> {
+        self.lookup.definitions.iter().find_map(|j| match j {
+            Jumpable::FunDef(id, params) if id.matches(name) => Some((id.clone(), params.clone())),
+            _ => None,
+        })
+    }
+
+    // find_references returns each usage of name within the script without resolving its definition
+    pub fn find_references<'a>(
+        &'a self,
+        name: &'a str,
+    ) -> impl Iterator<Item = &'a Identifier> + 'a {
+        self.references.iter().filter(move |i| i.matches(name))
+    }
 }
 
 #[cfg(test)]
 mod tests {
     use tree_sitter::Point;
 
-    use crate::{
-        interpret::NASLInterpreter,
-        types::to_pos,
-    };
+    use crate::interpret::NASLInterpreter;
 
     use super::SearchParameter;
 
@@ -190,6 +365,42 @@ mod tests {
         );
     }
 
+    #[test]
+    fn long_lines() {
+        // script_tag lines easily exceed 100 columns
+        let padding = " ".repeat(120);
+        let code = format!("a = 1;\n{padding}b = a;\nc = 2; {padding}if (c) {{ d = c; e = d; }}\n");
+        let js = NASLInterpreter::new("aha.nasl", &code).unwrap();
+        assert_eq!(
+            NASLInterpreter::search_parameter("aha.nasl", &code, 1, 124).map(|i| i.name),
+            Some("a")
+        );
+        assert_eq!(
+            js.find_points(&str_to_defco("a", 1, 124)).collect::<Vec<Point>>(),
+            vec![Point { row: 0, column: 0 }],
+        );
+        assert_eq!(
+            js.find_points(&str_to_defco("d", 2, 145)).collect::<Vec<Point>>(),
+            vec![Point { row: 2, column: 136 }],
+        );
+    }
+
+    #[test]
+    fn read() {
+        let dir = tempfile::tempdir().unwrap();
+        let root = dir.path().to_path_buf();
+        let utf8 = root.join("utf8.nasl");
+        let latin1 = root.join("latin1.nasl");
+        std::fs::write(&utf8, "# Jörg\n").unwrap();
+        std::fs::write(&latin1, b"# J\xf6rg\n").unwrap();
+        for path in [utf8, latin1] {
+            assert_eq!(
+                NASLInterpreter::read(path.to_str().unwrap()).unwrap(),
+                "# Jörg\n"
+            );
+        }
+    }
+
     #[test]
     fn find_calls() {
         let code = r#"
@@ -205,11 +416,35 @@ mod tests {
         assert_eq!(js.lookup.includes[0], "testus".to_string());
     }
 
-    fn str_to_defco(name: &str, line: usize, column: usize) -> SearchParameter {
+    #[test]
+    fn nested_calls() {
+        let code = r#"
+            function f(x) {
+                return a(b(x));
+            }
+            local_var l = c();
+            d[e()] += g(h: i());
+            foreach x (j()) {}
+            while (k()) {}
+            if (m() && (n = o())) display(n);
+            "#;
+        let js = NASLInterpreter::new("aha.nasl", code).unwrap();
+        let mut names: Vec<String> = js
+            .all_calls()
+            .filter_map(|(id, _)| id.identifier.clone())
+            .collect();
+        names.sort();
+        assert_eq!(
+            names,
+            vec!["a", "b", "c", "display", "e", "g", "i", "j", "k", "m", "o"]
+        );
+    }
+
+    fn str_to_defco(name: &str, line: usize, column: usize) -> SearchParameter<'_> {
         SearchParameter {
             origin: "aha.nasl",
             name,
-            pos: to_pos(line, column),
+            pos: Point { row: line, column },
         }
     }
 
@@ -297,4 +532,160 @@ mod tests {
         );
     }
 
+    #[test]
+    fn definitions_assigned_before() {
+        let code = r#"
+            b = 12;
+            display(b);
+            b = 13;
+            display(c);
+            c = 1;
+            "#;
+        let js = NASLInterpreter::new("aha.nasl", code).unwrap();
+        let points = |name, line, column| -> Vec<Point> {
+            js.find_points(&str_to_defco(name, line, column)).collect()
+        };
+        // later assignments are no definition of an earlier usage
+        assert_eq!(points("b", 2, 20), vec![Point { row: 1, column: 12 }]);
+        // without an earlier assignment the later ones are the definitions
+        assert_eq!(points("c", 4, 20), vec![Point { row: 5, column: 12 }]);
+    }
+
+    #[test]
+    fn declarations() {
+        let code = r#"
+            x = 1;
+            function f() {
+                local_var x;
+                x = 2;
+                return x;
+            }
+            function g() {
+                global_var y;
+                y = x;
+            }
+            display(y);
+            "#;
+        let js = NASLInterpreter::new("aha.nasl", code).unwrap();
+        let points = |name, line, column| {
+            js.find_points(&str_to_defco(name, line, column))
+                .collect::<Vec<Point>>()
+        };
+        // local_var shadows the global variable
+        assert_eq!(
+            points("x", 5, 23),
+            vec![Point { row: 3, column: 26 }, Point { row: 4, column: 16 }],
+        );
+        assert_eq!(points("x", 9, 20), vec![Point { row: 1, column: 12 }]);
+        // global_var declares a global variable within a function
+        assert_eq!(points("y", 11, 20), vec![Point { row: 8, column: 27 }]);
+    }
+
+    #[test]
+    fn loops() {
+        let code = r#"
+            foreach item (list) {
+                a = item;
+            }
+            for (i = 0; i < 3; i++) b = i;
+            while (x = recv()) { c = x; }
+            repeat { d = 1; } until (d > 1);
+            display(item, a, b);
+            "#;
+        let js = NASLInterpreter::new("aha.nasl", code).unwrap();
+        let points = |name, line, column| {
+            js.find_points(&str_to_defco(name, line, column))
+                .collect::<Vec<Point>>()
+        };
+        assert_eq!(points("item", 2, 20), vec![Point { row: 1, column: 20 }]);
+        // loop variables stay defined after the loop
+        assert_eq!(points("item", 7, 20), vec![Point { row: 1, column: 20 }]);
+        assert_eq!(points("a", 7, 26), vec![Point { row: 2, column: 16 }]);
+        assert_eq!(points("b", 7, 29), vec![Point { row: 4, column: 36 }]);
+        assert_eq!(points("i", 4, 40), vec![Point { row: 4, column: 17 }]);
+        assert_eq!(points("x", 5, 37), vec![Point { row: 5, column: 19 }]);
+        assert_eq!(points("d", 6, 37), vec![Point { row: 6, column: 21 }]);
+    }
+
+    #[test]
+    fn references() {
+        let code = r#"
+            function test(a) {
+                return a;
+            }
+            a = test(a: 1);
+            test(a);
+            "#;
+        let js = NASLInterpreter::new("aha.nasl", code).unwrap();
+        // neither the parameter nor the name of a named argument are usages
+        assert_eq!(
+            js.find_references("a")
+                .map(|i| i.start)
+                .collect::<Vec<Point>>(),
+            vec![
+                Point { row: 2, column: 23 },
+                Point { row: 4, column: 12 },
+                Point { row: 5, column: 17 },
+            ],
+        );
+        assert_eq!(js.find_references("test").count(), 2);
+    }
+
+    #[test]
+    fn circular_includes() {
+        let dir = tempfile::tempdir().unwrap();
+        let root = dir.path().to_path_buf();
+        let files = [
+            ("a.inc", "include(\"b.inc\");\ninclude(\"c.inc\");"),
+            ("b.inc", "include(\"a.inc\");\ninclude(\"c.inc\");"),
+            ("c.inc", "c = 1;"),
+        ];
+        for (name, code) in files {
+            std::fs::write(root.join(name), code).unwrap();
+        }
+        let root = root.to_str().unwrap().to_string();
+        let mut load = |p: &str| NASLInterpreter::new(p, &NASLInterpreter::read(p)?);
+        let a = format!("{root}/a.inc");
+        let origins: Vec<String> =
+            NASLInterpreter::new_with_includes(&a, vec![root.clone()], &mut load)
+                .unwrap()
+                .into_iter()
+                .map(|i| i.origin())
+                .collect();
+        assert_eq!(
+            origins,
+            vec![a.clone(), format!("{root}/b.inc"), format!("{root}/c.inc")]
+        );
+        assert_eq!(
+            NASLInterpreter::include_cycles(&a, vec![root.clone()], &mut load).unwrap(),
+            vec![vec![a.clone(), format!("{root}/b.inc"), a.clone()]]
+        );
+    }
+
+    #[test]
+    fn function_documentation() {
+        let code = r#"
+            # Returns a.
+            #
+            # a: the value to return
+            function test(a, b) {
+                return a;
+            }
+            "#;
+        let js = NASLInterpreter::new("aha.nasl", code).unwrap();
+        let (id, params) = js.function("test").unwrap();
+        assert_eq!(id.start, Point { row: 4, column: 12 });
+        assert_eq!(
+            params
+                .iter()
+                .filter_map(|p| p.identifier.clone())
+                .collect::<Vec<String>>(),
+            vec!["a".to_string(), "b".to_string()]
+        );
+        assert_eq!(
+            NASLInterpreter::leading_comment(code, id.start.row),
+            Some("Returns a.\n\na: the value to return".to_string())
+        );
+        assert_eq!(NASLInterpreter::leading_comment(code, 5), None);
+    }
 }
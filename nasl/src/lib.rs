@@ -1,6 +1,15 @@
+pub mod actions;
 pub mod cache;
+pub mod completion;
+pub mod diagnostics;
+pub mod document;
+pub mod index;
 pub mod interpret;
 mod lookup;
 pub mod openvas_funcs;
+pub mod position;
+pub mod signature;
+pub mod symbols;
 mod node_ext;
 pub mod types;
+pub mod variables;
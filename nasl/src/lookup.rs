@@ -1,13 +1,17 @@
-use tree_sitter::Node;
+use tree_sitter::{Node, Point};
 
 use crate::{
-    types::{Argument, Identifier}, interpret::SearchParameter, node_ext::JumpableExt,
+    types::{Argument, Definition, Identifier}, interpret::SearchParameter, node_ext::JumpableExt,
 };
 #[derive(Clone, Debug)]
 pub enum Jumpable {
     FunDef(Identifier, Vec<Identifier>),
     IfDef(Identifier, Vec<Identifier>),
     Assign(Identifier),
+    // LocalVar is a variable declared by local_var
+    LocalVar(Identifier),
+    // GlobalVar is a variable declared by global_var
+    GlobalVar(Identifier),
     Block((Identifier, Lookup)),
     CallExpression(Identifier, Vec<Argument>),
 }
@@ -64,18 +68,56 @@ fn verify_args(
     result
 }
 
+// declared_local returns true when a block containing sp declares its name by local_var
+pub fn declared_local(definitions: &[Jumpable], origin: &str, sp: &SearchParameter) -> bool {
+    origin == sp.origin
+        && definitions.iter().any(|j| match j {
+            Jumpable::Block((id, js)) if id.in_pos(sp.pos) => {
+                js.definitions
+                    .iter()
+                    .any(|j| matches!(j, Jumpable::LocalVar(id) if id.matches(sp.name)))
+                    || declared_local(&js.definitions, &js.origin, sp)
+            }
+            _ => false,
+        })
+}
+
+// global_declarations returns each global_var declaration of name within definitions and
+// their blocks
+fn global_declarations(definitions: &[Jumpable], name: &str) -> Vec<Identifier> {
+    definitions
+        .iter()
+        .flat_map(|j| match j {
+            Jumpable::GlobalVar(id) if id.matches(name) => vec![id.clone()],
+            Jumpable::Block((_, js)) => global_declarations(&js.definitions, name),
+            _ => vec![],
+        })
+        .collect()
+}
+
+// find_definitions returns the definitions of sp. A variable declared by local_var within a block
+// containing sp shadows the definitions outside of that block. Variables declared by global_var
+// are found within every block.
 pub fn find_definitions<'a>(
     definitions: &'a [Jumpable],
     origin: &'a str,
     sp: &'a SearchParameter,
 ) -> impl Iterator<Item = Identifier> + 'a {
+    let before = |id: &Identifier| id.start <= sp.pos;
+    // assignments within the same file have to be done before the usage unless there are none
+    let assigned_before = origin == sp.origin
+        && definitions
+            .iter()
+            .any(|j| matches!(j, Jumpable::Assign(id) if id.matches(sp.name) && before(id)));
+    let shadowed = declared_local(definitions, origin, sp);
     definitions.iter().flat_map(move |i| {
         let mut result = vec![];
         match i {
-            Jumpable::Block((id, js)) => {
-                if origin == sp.origin && id.in_pos(sp.pos) {
-                    result.extend(find_definitions(&js.definitions, &js.origin, sp));
-                }
+            Jumpable::Block((id, js)) if origin == sp.origin && id.in_pos(sp.pos) => {
+                result.extend(find_definitions(&js.definitions, &js.origin, sp));
+            }
+            Jumpable::Block((_, js)) if !shadowed => {
+                result.extend(global_declarations(&js.definitions, sp.name));
             }
             Jumpable::IfDef(id, params) => {
                 result.extend(verify_args(id, origin, params, sp));
@@ -83,12 +125,15 @@ pub fn find_definitions<'a>(
             Jumpable::FunDef(id, params) => {
                 result.extend(verify_args(id, origin, params, sp));
             }
-            Jumpable::Assign(id) => {
-                // TODO when need the information if it is in the same file
-                // if so control that the definition was done before
-                if id.matches(sp.name) {
-                    result.push(id.clone());
-                }
+            Jumpable::LocalVar(id) | Jumpable::GlobalVar(id)
+                if id.matches(sp.name) && !shadowed =>
+            {
+                result.push(id.clone());
+            }
+            Jumpable::Assign(id)
+                if id.matches(sp.name) && !shadowed && (!assigned_before || before(id)) =>
+            {
+                result.push(id.clone());
             }
             _ => {}
         }
@@ -96,6 +141,50 @@ pub fn find_definitions<'a>(
     })
 }
 
+// visible_definitions returns the definitions that are visible at pos.
+// Without a position only the definitions of the outer scope are returned.
+pub fn visible_definitions(definitions: &[Jumpable], pos: Option<Point>) -> Vec<Definition> {
+    let within = |id: &Identifier| pos.map(|p| id.in_pos(p)).unwrap_or_default();
+    definitions
+        .iter()
+        .flat_map(|i| {
+            let mut result = vec![];
+            match i {
+                Jumpable::Block((id, js)) if within(id) => {
+                    result.extend(visible_definitions(&js.definitions, pos));
+                }
+                Jumpable::IfDef(id, params) if within(id) => {
+                    result.extend(params.iter().cloned().map(Definition::Variable));
+                }
+                Jumpable::FunDef(id, params) => {
+                    result.push(Definition::Function(id.clone(), params.clone()));
+                    if within(id) {
+                        result.extend(params.iter().cloned().map(Definition::Variable));
+                    }
+                }
+                Jumpable::Assign(id) | Jumpable::LocalVar(id) | Jumpable::GlobalVar(id) => {
+                    result.push(Definition::Variable(id.clone()))
+                }
+                _ => {}
+            }
+            result
+        })
+        .collect()
+}
+
+// nested_calls returns the calls of lookup and of all blocks within it
+pub fn nested_calls(lookup: &Lookup) -> Box<dyn Iterator<Item = &Jumpable> + '_> {
+    Box::new(
+        lookup
+            .calls
+            .iter()
+            .chain(lookup.definitions.iter().flat_map(|j| match j {
+                Jumpable::Block((_, js)) => nested_calls(js),
+                _ => Box::new(std::iter::empty()),
+            })),
+    )
+}
+
 pub fn find_calls<'a>(
     calls: &'a [Jumpable],
     name: &'a str,
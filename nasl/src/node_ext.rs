@@ -71,7 +71,7 @@ impl FuncDeclaratorExt for Node<'_> {
                 }
             });
         }
-        return vec![];
+        vec![]
     }
 }
 
@@ -169,6 +169,16 @@ impl CallExpressionExt for Node<'_> {
             return walk_named_children(self, |c, r| {
                 if let Some(sl) = c.string_literal(container) {
                     r.push(sl);
+                } else if c.kind() == "named_argument" {
+                    if let Some(id) = c.named_child(0).and_then(|n| n.identifier(container)) {
+                        r.push(Argument::Named(id));
+                    }
+                } else if !c.is_extra() {
+                    r.push(Argument::Positional(Identifier {
+                        start: c.start_position(),
+                        end: c.end_position(),
+                        identifier: None,
+                    }));
                 }
             });
         }
@@ -190,6 +200,87 @@ impl CallExpressionExt for Node<'_> {
     }
 }
 
+trait DeclarationExt {
+    fn declaration(self, container: &CodeContainer<'_>) -> Vec<Jumpable>;
+}
+
+impl DeclarationExt for Node<'_> {
+    // declaration returns a definition for each variable declared by local_var or global_var
+    fn declaration(self, container: &CodeContainer<'_>) -> Vec<Jumpable> {
+        if self.kind() != "declaration" {
+            return vec![];
+        }
+        let global = self
+            .child_by_field_name("type")
+            .map(|t| &container.code[t.byte_range()] == "global_var")
+            .unwrap_or_default();
+        let mut result = vec![];
+        let crsr = &mut self.walk();
+        for d in self.children_by_field_name("declarator", crsr) {
+            let id = match d.kind() {
+                "init_declarator" => {
+                    if let Some(v) = d.child_by_field_name("value") {
+                        result.extend(v.expression(container));
+                    }
+                    d.child_by_field_name("declarator").and_then(|i| i.identifier(container))
+                }
+                _ => d.identifier(container),
+            };
+            result.extend(id.map(|id| {
+                if global { Jumpable::GlobalVar(id) } else { Jumpable::LocalVar(id) }
+            }));
+        }
+        result
+    }
+}
+
+trait ExpressionExt {
+    fn expression(self, container: &CodeContainer<'_>) -> Vec<Jumpable>;
+}
+
+impl ExpressionExt for Node<'_> {
+    // expression returns each call and assignment within the expression tree of the node,
+    // e.g. calls within arguments, subscripts or the right side of an assignment
+    fn expression(self, container: &CodeContainer<'_>) -> Vec<Jumpable> {
+        let mut result = match self.kind() {
+            // blocks and functions are handled by their own lookup
+            "compound_statement" | "function_definition" => return vec![],
+            "call_expression" => {
+                let mut result = self.call_expression(container);
+                if let Some(c) = self.child_by_field_name("arguments") {
+                    result.extend(c.expression(container));
+                }
+                return result;
+            }
+            "assignment_expression" => self.assignment_expression(container),
+            _ => vec![],
+        };
+        result.extend(walk_named_children(self, |c, r| r.extend(c.expression(container))));
+        result
+    }
+}
+
+trait StatementExt {
+    fn statement(self, container: &CodeContainer<'_>) -> Vec<Jumpable>;
+}
+
+impl StatementExt for Node<'_> {
+    // statement returns the definitions and calls of a statement within a block or of the body
+    // of an if statement or loop
+    fn statement(self, container: &CodeContainer<'_>) -> Vec<Jumpable> {
+        let mut result = vec![];
+        result.extend(self.expression_statement(container));
+        result.extend(self.compound_statement(container));
+        result.extend(self.if_statement(container));
+        result.extend(self.declaration(container));
+        result.extend(self.loop_statement(container));
+        if self.kind() == "return_statement" {
+            result.extend(walk_named_children(self, |c, r| r.extend(c.expression(container))));
+        }
+        result
+    }
+}
+
 trait ExpressionStatementExt {
     fn expression_statement(self, container: &CodeContainer<'_>) -> Vec<Jumpable>;
 }
@@ -197,10 +288,7 @@ trait ExpressionStatementExt {
 impl ExpressionStatementExt for Node<'_> {
     fn expression_statement(self, container: &CodeContainer<'_>) -> Vec<Jumpable> {
         if self.kind() == "expression_statement" {
-            return walk_named_children(self, |c, r| {
-                r.extend(c.call_expression(container));
-                r.extend(c.assignment_expression(container))
-            });
+            return walk_named_children(self, |c, r| r.extend(c.expression(container)));
         }
         vec![]
     }
@@ -262,15 +350,52 @@ impl IfStatementExt for Node<'_> {
                     assignments,
                 );
                 result.push(ifdef);
+                result.extend(
+                    c.expression(container)
+                        .into_iter()
+                        .filter(|j| matches!(j, Jumpable::CallExpression(_, _))),
+                );
             }
             if let Some(c) = self.child_by_field_name("consequence") {
-                result.extend(c.compound_statement(container));
-                result.extend(c.expression_statement(container));
+                result.extend(c.statement(container));
             }
             if let Some(c) = self.child_by_field_name("alternative") {
-                result.extend(c.if_statement(container));
-                result.extend(c.compound_statement(container));
-                result.extend(c.expression_statement(container));
+                result.extend(c.statement(container));
+            }
+        }
+        result
+    }
+}
+
+trait LoopStatementExt {
+    fn loop_statement(self, container: &CodeContainer<'_>) -> Vec<Jumpable>;
+}
+
+impl LoopStatementExt for Node<'_> {
+    // loop_statement returns the definitions and calls of for, foreach, while and repeat loops.
+    // NASL has no block scope, therefore the foreach element as well as assignments within the
+    // initializer, condition or body stay defined after the loop.
+    fn loop_statement(self, container: &CodeContainer<'_>) -> Vec<Jumpable> {
+        if !matches!(
+            self.kind(),
+            "for_statement" | "foreach_statement" | "while_statement" | "repeat_statement"
+        ) {
+            return vec![];
+        }
+        let mut result = vec![];
+        if let Some(c) = self.child_by_field_name("element") {
+            result.extend(c.identifier(container).map(Jumpable::Assign));
+        }
+        for field in ["selection", "initializer", "condition", "update"] {
+            if let Some(c) = self.child_by_field_name(field) {
+                result.extend(c.expression(container));
+            }
+        }
+        if let Some(c) = self.child_by_field_name("body") {
+            if c.kind() == "compound_statement" {
+                result.extend(walk_named_children(c, |s, r| r.extend(s.statement(container))));
+            } else {
+                result.extend(c.statement(container));
             }
         }
         result
@@ -285,9 +410,136 @@ impl JumpableExt for Node<'_> {
     fn jumpable(self, container: &CodeContainer<'_>) -> Vec<Jumpable> {
         walk_named_children(self, |c, result| {
             result.extend(c.func_def(container));
-            result.extend(c.expression_statement(container));
-            result.extend(c.compound_statement(container));
-            result.extend(c.if_statement(container));
+            result.extend(c.statement(container));
         })
     }
 }
+
+pub trait ReferenceExt {
+    fn references(self, container: &CodeContainer<'_>) -> Vec<Identifier>;
+}
+
+impl ReferenceExt for Node<'_> {
+    // references collects every identifier used within the node.
+    // Function names within a declarator, parameter lists and the names of named arguments
+    // are skipped since they do not refer to a definition.
+    fn references(self, container: &CodeContainer<'_>) -> Vec<Identifier> {
+        if let Some(id) = self.identifier(container) {
+            return vec![id];
+        }
+        match self.kind() {
+            "function_declarator" | "parameter_list" => vec![],
+            "named_argument" => walk_named_children(self, |c, r| {
+                if c.kind() != "identifier" {
+                    r.extend(c.references(container));
+                }
+            }),
+            _ => walk_named_children(self, |c, r| r.extend(c.references(container))),
+        }
+    }
+}
+
+pub trait RequiredParameterExt {
+    fn required_parameters(self, code: &str) -> Vec<String>;
+}
+
+// ParameterUsage contains the parameters of a function that are read unconditionally and those
+// that are tested or assigned within the function body
+struct ParameterUsage {
+    parameters: Vec<String>,
+    read: Vec<String>,
+    tested: Vec<String>,
+}
+
+// parameter_usage walks node and records the usage of parameters.
+// conditional is set within branches and loops, tested within conditions, negations, isnull calls
+// and the left side of assignments.
+fn parameter_usage(
+    node: Node<'_>,
+    code: &str,
+    conditional: bool,
+    tested: bool,
+    usage: &mut ParameterUsage,
+) {
+    if node.kind() == "identifier" {
+        let name = code[node.byte_range()].to_string();
+        if usage.parameters.contains(&name) {
+            if tested {
+                usage.tested.push(name);
+            } else if !conditional {
+                usage.read.push(name);
+            }
+        }
+        return;
+    }
+    let operator = node
+        .child_by_field_name("operator")
+        .map(|o| &code[o.byte_range()])
+        .unwrap_or_default();
+    let function = node
+        .child_by_field_name("function")
+        .map(|f| &code[f.byte_range()])
+        .unwrap_or_default();
+    let logical = operator == "&&" || operator == "||";
+    let crsr = &mut node.walk();
+    if !crsr.goto_first_child() {
+        return;
+    }
+    loop {
+        let (c, t) = match (node.kind(), crsr.field_name()) {
+            ("if_statement" | "while_statement" | "repeat_statement", Some("condition")) => {
+                (conditional, true)
+            }
+            ("for_statement", Some("condition")) => (true, true),
+            ("if_statement", Some("consequence" | "alternative")) => (true, tested),
+            ("while_statement" | "foreach_statement", Some("body")) => (true, tested),
+            ("for_statement", Some("update" | "body")) => (true, tested),
+            ("assignment_expression", Some("left")) => (conditional, true),
+            ("unary_expression", Some("argument")) if operator == "!" => (conditional, true),
+            ("binary_expression", Some("left")) if logical => (conditional, true),
+            ("binary_expression", Some("right")) if logical => (true, true),
+            ("call_expression", Some("arguments")) if function == "isnull" => (conditional, true),
+            _ => (conditional, tested),
+        };
+        let child = crsr.node();
+        if child.is_named() {
+            parameter_usage(child, code, c, t, usage);
+        }
+        if !crsr.goto_next_sibling() {
+            break;
+        }
+    }
+}
+
+impl RequiredParameterExt for Node<'_> {
+    // required_parameters returns the parameters of a function definition that are always read
+    // and neither tested nor assigned; those are expected to be set by each caller.
+    fn required_parameters(self, code: &str) -> Vec<String> {
+        if self.kind() != "function_definition" {
+            return vec![];
+        }
+        let parameters = self
+            .child_by_field_name("declarator")
+            .and_then(|d| d.child_by_field_name("parameters"))
+            .map(|p| {
+                walk_named_children(p, |c, r| {
+                    if c.kind() == "identifier" {
+                        r.push(code[c.byte_range()].to_string());
+                    }
+                })
+            })
+            .unwrap_or_default();
+        let mut usage = ParameterUsage {
+            parameters: parameters.clone(),
+            read: vec![],
+            tested: vec![],
+        };
+        if let Some(body) = self.child_by_field_name("body") {
+            parameter_usage(body, code, false, false, &mut usage);
+        }
+        parameters
+            .into_iter()
+            .filter(|p| usage.read.contains(p) && !usage.tested.contains(p))
+            .collect()
+    }
+}
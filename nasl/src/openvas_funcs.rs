@@ -1,11 +1,13 @@
-use std::{error::Error, fs, ops::Range};
+use std::{collections::HashMap, error::Error, fs, ops::Range, path::Path};
 
-use tracing::debug;
+use tracing::{debug, trace};
 use tree_sitter::{Node, Point};
+use walkdir::WalkDir;
 
 use crate::{
-    interpret::{tree, SearchParameter},
+    interpret::{tree, NASLInterpreter, SearchParameter},
     lookup::{find_definitions, Jumpable},
+    signature::{Parameter, Signature},
     types::Identifier,
 };
 
@@ -13,6 +15,297 @@ use crate::{
 pub struct OpenVASInterpreter {
     definitions: Vec<Jumpable>,
     origin: String,
+    builtins: HashMap<String, Builtin>,
+    // constants contains the variables predefined by the interpreter
+    constants: Vec<String>,
+}
+
+// Builtin describes a function that is implemented within openvas-scanner
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct Builtin {
+    pub name: String,
+    // function is the name of the implementing C function
+    pub function: String,
+    // source is the file and position of the implementing C function
+    pub source: Option<(String, Point)>,
+    pub documentation: Option<String>,
+    // named contains the names of the named arguments that are read by the C function
+    pub named: Vec<String>,
+    // required contains the named arguments without which the C function returns early
+    pub required: Vec<String>,
+    // positional is the amount of unnamed arguments that are read by the C function
+    pub positional: usize,
+    // resolved is set when named contains each named argument the C function can read; it is
+    // unset when a name is computed or lexic is passed to a function that is not found
+    pub resolved: bool,
+}
+
+impl Builtin {
+    pub fn signature(&self) -> Signature {
+        let mut parameters: Vec<Parameter> = (1..=self.positional)
+            .map(|i| Parameter {
+                name: format!("arg{i}"),
+                named: false,
+                required: false,
+            })
+            .collect();
+        parameters.extend(self.named.iter().map(|n| Parameter {
+            name: n.clone(),
+            named: true,
+            required: self.required.contains(n),
+        }));
+        Signature {
+            name: self.name.clone(),
+            parameters,
+            documentation: self.documentation.clone(),
+        }
+    }
+}
+
+// CFunction is a C function definition found within the openvas-scanner sources
+struct CFunction {
+    name: String,
+    start: Point,
+    comment: Option<String>,
+    reads: Reads,
+}
+
+// Reads contains the arguments read within a C function
+#[derive(Default)]
+struct Reads {
+    // named contains each read named argument, the variable it is assigned to and if it is read
+    // unconditionally
+    named: Vec<(String, Option<String>, bool)>,
+    positional: usize,
+    // guarded contains the variables checked by an if statement that returns
+    guarded: Vec<String>,
+    // delegates contains the functions lexic is passed to and if they are called unconditionally
+    delegates: Vec<(String, bool)>,
+    // dynamic is set when a named argument is read by a name that is no string literal
+    dynamic: bool,
+}
+
+// clean_comment removes the comment markers of a C block comment
+fn clean_comment(comment: &str) -> String {
+    comment
+        .trim_start_matches("/**")
+        .trim_start_matches("/*")
+        .trim_end_matches("*/")
+        .lines()
+        .map(|l| l.trim().trim_start_matches('*').trim())
+        .collect::<Vec<&str>>()
+        .join("\n")
+        .trim()
+        .to_string()
+}
+
+// c_function_name returns the identifier of a C function declarator
+fn c_function_name<'a>(node: &Node<'a>) -> Option<Node<'a>> {
+    match node.kind() {
+        "identifier" => Some(*node),
+        "pointer_declarator" | "function_declarator" => node
+            .child_by_field_name("declarator")
+            .and_then(|d| c_function_name(&d)),
+        _ => None,
+    }
+}
+
+// NAMED_GETTERS are the functions used by builtins to read a named argument
+const NAMED_GETTERS: [&str; 4] = [
+    "get_str_var_by_name",
+    "get_int_var_by_name",
+    "get_var_size_by_name",
+    "get_var_type_by_name",
+];
+
+// POSITIONAL_GETTERS are the functions used by builtins to read an unnamed argument
+const POSITIONAL_GETTERS: [&str; 4] = [
+    "get_str_var_by_num",
+    "get_int_var_by_num",
+    "get_var_size_by_num",
+    "get_var_type_by_num",
+];
+
+// REPORTERS are the functions lexic is passed to only to report errors; they read no arguments
+const REPORTERS: [&str; 2] = ["nasl_perror", "nasl_trace"];
+
+// assigned_variable returns the name of the variable the result of call is assigned to
+fn assigned_variable(code: &str, call: &Node<'_>) -> Option<String> {
+    let parent = call.parent()?;
+    let variable = match parent.kind() {
+        "init_declarator" => c_function_name(&parent.child_by_field_name("declarator")?),
+        "assignment_expression" => parent.child_by_field_name("left"),
+        _ => None,
+    }?;
+    Some(code[variable.byte_range()].to_string())
+}
+
+// identifiers returns the text of each identifier within node
+fn identifiers(code: &str, node: &Node<'_>) -> Vec<String> {
+    if node.kind() == "identifier" {
+        return vec![code[node.byte_range()].to_string()];
+    }
+    let crsr = &mut node.walk();
+    node.named_children(crsr)
+        .flat_map(|c| identifiers(code, &c))
+        .collect()
+}
+
+// contains_return checks if there is a return statement within node
+fn contains_return(node: &Node<'_>) -> bool {
+    let crsr = &mut node.walk();
+    node.kind() == "return_statement" || node.named_children(crsr).any(|c| contains_return(&c))
+}
+
+// c_call records the argument read or the delegation done by the call expression node
+fn c_call(code: &str, node: &Node<'_>, conditional: bool, reads: &mut Reads) {
+    let function = node
+        .child_by_field_name("function")
+        .map(|f| &code[f.byte_range()])
+        .unwrap_or_default();
+    let arguments = match node.child_by_field_name("arguments") {
+        Some(a) => a,
+        None => return,
+    };
+    match arguments.named_child(1) {
+        Some(a) if a.kind() == "string_literal" && NAMED_GETTERS.contains(&function) => {
+            let name = code[string_literal_range(&a.byte_range())].to_string();
+            let variable = assigned_variable(code, node);
+            reads.named.push((name, variable, !conditional));
+            return;
+        }
+        Some(_) if NAMED_GETTERS.contains(&function) => {
+            reads.dynamic = true;
+            return;
+        }
+        Some(a) if a.kind() == "number_literal" && POSITIONAL_GETTERS.contains(&function) => {
+            if let Ok(i) = code[a.byte_range()].parse::<usize>() {
+                reads.positional = reads.positional.max(i + 1);
+            }
+            return;
+        }
+        _ => {}
+    }
+    let crsr = &mut arguments.walk();
+    let delegates = arguments
+        .named_children(crsr)
+        .any(|a| a.kind() == "identifier" && &code[a.byte_range()] == "lexic");
+    let getter = POSITIONAL_GETTERS.contains(&function) || NAMED_GETTERS.contains(&function);
+    if delegates && !getter && !REPORTERS.contains(&function) {
+        reads.delegates.push((function.to_string(), !conditional));
+    }
+}
+
+// c_arguments collects the arguments read within node.
+// conditional is set within branches and loops.
+fn c_arguments(code: &str, node: &Node<'_>, conditional: bool, reads: &mut Reads) {
+    match node.kind() {
+        "call_expression" => c_call(code, node, conditional, reads),
+        "if_statement" => {
+            let returns = node
+                .child_by_field_name("consequence")
+                .map(|c| contains_return(&c))
+                .unwrap_or_default();
+            if let Some(condition) = node.child_by_field_name("condition").filter(|_| returns) {
+                reads.guarded.extend(identifiers(code, &condition));
+            }
+        }
+        _ => {}
+    }
+    let operator = node
+        .child_by_field_name("operator")
+        .map(|o| &code[o.byte_range()])
+        .unwrap_or_default();
+    let crsr = &mut node.walk();
+    if !crsr.goto_first_child() {
+        return;
+    }
+    loop {
+        let branch = match (node.kind(), crsr.field_name()) {
+            ("if_statement" | "while_statement" | "switch_statement", Some("condition")) => false,
+            ("if_statement" | "while_statement" | "switch_statement", _) => true,
+            ("for_statement" | "do_statement" | "case_statement", _) => true,
+            ("conditional_expression", Some("consequence" | "alternative")) => true,
+            ("binary_expression", Some("right")) => operator == "&&" || operator == "||",
+            _ => false,
+        };
+        let child = crsr.node();
+        if child.is_named() {
+            c_arguments(code, &child, conditional || branch, reads);
+        }
+        if !crsr.goto_next_sibling() {
+            break;
+        }
+    }
+}
+
+// c_functions returns each C function definition within node
+fn c_functions(code: &str, node: &Node<'_>) -> Vec<CFunction> {
+    let crsr = &mut node.walk();
+    node.named_children(crsr)
+        .filter(|c| c.kind() == "function_definition")
+        .filter_map(|c| {
+            let name = c
+                .child_by_field_name("declarator")
+                .and_then(|d| c_function_name(&d))?;
+            let comment = c
+                .prev_named_sibling()
+                .filter(|p| p.kind() == "comment")
+                .map(|p| clean_comment(&code[p.byte_range()]));
+            let mut reads = Reads::default();
+            if let Some(body) = c.child_by_field_name("body") {
+                c_arguments(code, &body, false, &mut reads);
+            }
+            Some(CFunction {
+                name: code[name.byte_range()].to_string(),
+                start: name.start_position(),
+                comment,
+                reads,
+            })
+        })
+        .collect()
+}
+
+// resolve_arguments sets the arguments of builtin read by function and the functions it
+// delegates to. Named arguments that are read unconditionally and checked before returning
+// are required. It returns false when not all named arguments could be found.
+fn resolve_arguments(
+    builtin: &mut Builtin,
+    function: &str,
+    functions: &HashMap<String, CFunction>,
+    unconditional: bool,
+    visited: &mut Vec<String>,
+) -> bool {
+    let reads = match functions.get(function) {
+        Some(f) if !visited.contains(&f.name) => &f.reads,
+        Some(_) => return true,
+        None => return false,
+    };
+    visited.push(function.to_string());
+    for (name, variable, always) in &reads.named {
+        if !builtin.named.contains(name) {
+            builtin.named.push(name.clone());
+        }
+        let guarded = variable
+            .as_ref()
+            .map(|v| reads.guarded.contains(v))
+            .unwrap_or_default();
+        if unconditional && *always && guarded && !builtin.required.contains(name) {
+            builtin.required.push(name.clone());
+        }
+    }
+    builtin.positional = builtin.positional.max(reads.positional);
+    let mut resolved = !reads.dynamic;
+    for (delegate, always) in &reads.delegates {
+        resolved &= resolve_arguments(
+            builtin,
+            delegate,
+            functions,
+            unconditional && *always,
+            visited,
+        );
+    }
+    resolved
 }
 
 fn string_literal_range(r: &Range<usize>) -> Range<usize> {
@@ -22,7 +315,36 @@ fn string_literal_range(r: &Range<usize>) -> Range<usize> {
     }
 }
 
-fn naslfuncnames(node: &Node<'_>, code: &str) -> Vec<(Range<usize>, Point)> {
+// naslvarnames returns the name of each predefined variable within libivars and libsvars
+fn naslvarnames(node: &Node<'_>, code: &str) -> Vec<String> {
+    let declarator = match node.child_by_field_name("declarator") {
+        Some(d) if node.kind() == "declaration" && d.kind() == "init_declarator" => d,
+        _ => return vec![],
+    };
+    let name = declarator
+        .child_by_field_name("declarator")
+        .and_then(|d| d.child_by_field_name("declarator"))
+        .map(|d| &code[d.byte_range()])
+        .unwrap_or_default();
+    if name != "libivars" && name != "libsvars" {
+        return vec![];
+    }
+    let value = match declarator.child_by_field_name("value") {
+        Some(v) if v.kind() == "initializer_list" => v,
+        _ => return vec![],
+    };
+    let crsr = &mut value.walk();
+    value
+        .named_children(crsr)
+        .filter_map(|v| v.named_child(0))
+        .filter(|v| v.kind() == "string_literal")
+        .map(|v| code[string_literal_range(&v.byte_range())].to_string())
+        .collect()
+}
+
+// naslfuncnames returns the byte range and position of each builtin name within libfuncs as well
+// as the byte range of the implementing C function
+fn naslfuncnames(node: &Node<'_>, code: &str) -> Vec<(Range<usize>, Point, Range<usize>)> {
     if node.kind() == "declaration" {
         if let Some(d) = node.child_by_field_name("declarator") {
             if d.kind() == "init_declarator" {
@@ -49,6 +371,7 @@ fn naslfuncnames(node: &Node<'_>, code: &str) -> Vec<(Range<usize>, Point)> {
                                                 return Some((
                                                     sl.byte_range(),
                                                     sl.start_position(),
+                                                    id.byte_range(),
                                                 ));
                                             }
                                         }
@@ -73,9 +396,84 @@ pub trait DefResponseContainer<T> {
 impl OpenVASInterpreter {
     pub fn from_path(path: &str) -> Result<OpenVASInterpreter, Box<dyn Error>> {
         debug!("parsing {} for internal functions", path);
-        let code = fs::read_to_string(path)?;
-        OpenVASInterpreter::new(path.to_string(), code)
+        let code = NASLInterpreter::read(path)?;
+        let mut result = OpenVASInterpreter::new(path.to_string(), code)?;
+        // nasl_init.c is located within the nasl directory of an openvas-scanner checkout
+        if let Some(nasl) = Path::new(path).parent() {
+            result.load_sources(nasl);
+            if let Some(root) = nasl.parent() {
+                result.load_manual(&root.join("doc/manual/nasl"));
+            }
+        }
+        Ok(result)
+    }
+
+    // load_sources sets the source, documentation comment and arguments of each builtin
+    // implemented within a C file of dir
+    pub fn load_sources(&mut self, dir: &Path) {
+        let files = match fs::read_dir(dir) {
+            Ok(entries) => entries.filter_map(|e| e.ok()).map(|e| e.path()),
+            Err(_) => return,
+        };
+        let mut functions: HashMap<String, CFunction> = HashMap::new();
+        let mut sources: HashMap<String, String> = HashMap::new();
+        for file in files.filter(|f| f.extension().map(|e| e == "c").unwrap_or_default()) {
+            let code = match NASLInterpreter::read(&file.to_string_lossy()) {
+                Ok(c) => c,
+                Err(_) => continue,
+            };
+            let tree = match tree(tree_sitter_c::language(), &code, None) {
+                Ok(t) => t,
+                Err(_) => continue,
+            };
+            trace!("looking for builtins in {}", file.display());
+            for f in c_functions(&code, &tree.root_node()) {
+                sources.insert(f.name.clone(), file.to_string_lossy().to_string());
+                functions.insert(f.name.clone(), f);
+            }
+        }
+        for b in self.builtins.values_mut() {
+            if let Some(f) = functions.get(&b.function) {
+                b.source = sources.get(&f.name).map(|s| (s.clone(), f.start));
+                b.documentation = b.documentation.clone().or_else(|| f.comment.clone());
+                let function = b.function.clone();
+                b.resolved = resolve_arguments(b, &function, &functions, true, &mut vec![]);
+            }
+        }
+    }
+
+    // load_manual sets the documentation of each builtin that is described by a markdown file
+    // named after it within dir
+    pub fn load_manual(&mut self, dir: &Path) {
+        for entry in WalkDir::new(dir).into_iter().filter_map(|e| e.ok()) {
+            let path = entry.path();
+            if path.extension().map(|e| e != "md").unwrap_or(true) {
+                continue;
+            }
+            let name = path
+                .file_stem()
+                .and_then(|s| s.to_str())
+                .unwrap_or_default();
+            if let Some(b) = self.builtins.get_mut(name) {
+                if let Ok(documentation) = fs::read_to_string(path) {
+                    b.documentation = Some(documentation);
+                }
+            }
+        }
+    }
+
+    pub fn builtin(&self, name: &str) -> Option<&Builtin> {
+        self.builtins.get(name)
+    }
+
+    pub fn is_constant(&self, name: &str) -> bool {
+        self.constants.iter().any(|c| c == name)
+    }
+
+    pub fn builtins(&self) -> impl Iterator<Item = &Builtin> + '_ {
+        self.builtins.values()
     }
+
     pub fn new(origin: String, code: String) -> Result<OpenVASInterpreter, Box<dyn Error>> {
         //let code = fs::read_to_string(path)?;
         let tree = tree(tree_sitter_c::language(), &code, None)?;
@@ -83,10 +481,22 @@ impl OpenVASInterpreter {
         let rnw = &mut rn.walk();
         let nc = rn.named_children(rnw);
         let mut definitions = vec![];
+        let mut builtins = HashMap::new();
+        let mut constants = vec![];
         for c in nc {
-            definitions.extend(naslfuncnames(&c, &code).iter().map(|(br, start)| {
+            constants.extend(naslvarnames(&c, &code));
+            definitions.extend(naslfuncnames(&c, &code).iter().map(|(br, start, fr)| {
+                let name = code[string_literal_range(br)].to_string();
+                builtins.insert(
+                    name.clone(),
+                    Builtin {
+                        name: name.clone(),
+                        function: code[fr.clone()].to_string(),
+                        ..Default::default()
+                    },
+                );
                 let id = Identifier {
-                    identifier: Some(code[string_literal_range(br)].to_string()),
+                    identifier: Some(name),
                     start: *start,
                     end: Point::default(),
                 };
@@ -100,10 +510,15 @@ impl OpenVASInterpreter {
         Ok(OpenVASInterpreter {
             definitions,
             origin,
+            builtins,
+            constants,
         })
     }
 
-    pub fn find_origin_location<'a>(&'a self, sp: &'a SearchParameter) -> impl Iterator<Item = (String, Point)> + 'a {
+    pub fn find_origin_location<'a>(
+        &'a self,
+        sp: &'a SearchParameter,
+    ) -> impl Iterator<Item = (String, Point)> + 'a {
         find_definitions(&self.definitions, &self.origin, sp)
             .map(|x| (self.origin.clone(), x.start))
     }
@@ -113,10 +528,9 @@ impl OpenVASInterpreter {
 mod tests {
     use tree_sitter::Point;
 
-
     use crate::interpret::SearchParameter;
 
-    use super::OpenVASInterpreter;
+    use super::{Builtin, OpenVASInterpreter};
 
     #[test]
     fn funcnames() {
@@ -124,16 +538,137 @@ mod tests {
         #include "nasl_me.h"
         #include <stdio.h>
         static init_func libfuncs[] = { {"script_name", script_name_internal} };
+        static init_nasl_ivars libivars[] = { {"TRUE", 1}, {"IPPROTO_TCP", IPPROTO_TCP} };
+        static init_nasl_svars libsvars[] = { {"OPENVAS_VERSION", OPENVAS_NASL_VERSION} };
         "#;
         let ut = OpenVASInterpreter::new("nasl_init.c".to_string(), code.to_string()).unwrap();
         let sp = SearchParameter {
             origin: "nasl_init.c",
             name: "script_name",
-            pos: 0.0,
+            pos: Point::default(),
         };
         assert_eq!(
             ut.find_origin_location(&sp).next(),
             Some(("nasl_init.c".to_string(), Point { row: 3, column: 41 })),
         );
+        assert!(ut.is_constant("IPPROTO_TCP"));
+        assert!(ut.is_constant("OPENVAS_VERSION"));
+        assert!(!ut.is_constant("script_name"));
+    }
+
+    #[test]
+    fn documentation() {
+        let dir = tempfile::tempdir().unwrap();
+        let root = dir.path().to_path_buf();
+        let nasl = root.join("nasl");
+        let manual = root.join("doc/manual/nasl/built-in-functions/misc");
+        std::fs::create_dir_all(&nasl).unwrap();
+        std::fs::create_dir_all(&manual).unwrap();
+        std::fs::write(
+            nasl.join("nasl_init.c"),
+            r#"static init_func libfuncs[] = { {"script_name", script_name}, {"rand", nasl_rand} };"#,
+        )
+        .unwrap();
+        let source = nasl.join("nasl_misc_funcs.c");
+        std::fs::write(
+            &source,
+            "/**\n * @brief Returns a random number.\n */\ntree_cell *\nnasl_rand (lex_ctxt *lexic)\n{\n}\n\ntree_cell *\nscript_name (lex_ctxt *lexic)\n{\n  char *name = get_str_var_by_num (lexic, 0);\n  if (get_int_var_by_name (lexic, \"append\", 0))\n    get_str_var_by_name (lexic, \"sep\");\n}\n",
+        )
+        .unwrap();
+        std::fs::write(manual.join("script_name.md"), "# script_name").unwrap();
+        let ut = OpenVASInterpreter::from_path(nasl.join("nasl_init.c").to_str().unwrap()).unwrap();
+        let source = source.to_str().unwrap().to_string();
+        assert_eq!(
+            ut.builtin("rand"),
+            Some(&Builtin {
+                name: "rand".to_string(),
+                function: "nasl_rand".to_string(),
+                source: Some((source.clone(), Point { row: 4, column: 0 })),
+                documentation: Some("@brief Returns a random number.".to_string()),
+                resolved: true,
+                ..Default::default()
+            })
+        );
+        assert_eq!(
+            ut.builtin("script_name"),
+            Some(&Builtin {
+                name: "script_name".to_string(),
+                function: "script_name".to_string(),
+                source: Some((source, Point { row: 9, column: 0 })),
+                documentation: Some("# script_name".to_string()),
+                named: vec!["append".to_string(), "sep".to_string()],
+                positional: 1,
+                resolved: true,
+                ..Default::default()
+            })
+        );
+    }
+
+    #[test]
+    fn arguments() {
+        let dir = tempfile::tempdir().unwrap();
+        let nasl = dir.path().to_path_buf();
+        std::fs::write(
+            nasl.join("nasl_init.c"),
+            r#"static init_func libfuncs[] = { {"http_get", http_get}, {"http_head", http_head},
+{"http_put", http_put} };"#,
+        )
+        .unwrap();
+        std::fs::write(
+            nasl.join("nasl_http.c"),
+            r#"
+tree_cell *
+http_get (lex_ctxt *lexic)
+{
+  return _http_req (lexic, "GET");
+}
+
+tree_cell *
+http_head (lex_ctxt *lexic)
+{
+  char *name = "item";
+  return get_str_var_by_name (lexic, name);
+}
+
+tree_cell *
+http_put (lex_ctxt *lexic)
+{
+  return _unknown_req (lexic, "PUT");
+}
+"#,
+        )
+        .unwrap();
+        std::fs::write(
+            nasl.join("nasl_http_req.c"),
+            r#"
+static tree_cell *
+_http_req (lex_ctxt *lexic, char *keyword)
+{
+  char *item = get_str_var_by_name (lexic, "item");
+  char *data = get_str_var_by_name (lexic, "data");
+  int port = get_int_var_by_name (lexic, "port", -1);
+  if (item == NULL || port < 0)
+    {
+      nasl_perror (lexic, "item and port are mandatory\n");
+      return NULL;
+    }
+  if (data)
+    port = get_int_var_by_name (lexic, "length", 0);
+}
+"#,
+        )
+        .unwrap();
+        let ut = OpenVASInterpreter::from_path(nasl.join("nasl_init.c").to_str().unwrap()).unwrap();
+        let builtin = ut.builtin("http_get").unwrap();
+        assert_eq!(builtin.named, vec!["item", "data", "port", "length"]);
+        assert_eq!(builtin.required, vec!["item", "port"]);
+        // nasl_perror only reports an error
+        assert!(builtin.resolved);
+        assert!(!ut.builtin("http_head").unwrap().resolved);
+        assert!(!ut.builtin("http_put").unwrap().resolved);
+        assert_eq!(
+            builtin.signature().label(),
+            "http_get(item:, data:, port:, length:)"
+        );
     }
 }
@@ -0,0 +1,176 @@
+use tree_sitter::Point;
+
+// Encoding is the unit the column of a position of the client is counted in.
+// Tree-sitter counts bytes while LSP defaults to UTF-16 code units.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum Encoding {
+    Utf8,
+    #[default]
+    Utf16,
+}
+
+impl Encoding {
+    // negotiate returns UTF-8 when the client supports it since no conversion is necessary then,
+    // otherwise the mandatory UTF-16
+    pub fn negotiate(supported: &[String]) -> Self {
+        if supported.iter().any(|s| s == "utf-8") {
+            Encoding::Utf8
+        } else {
+            Encoding::Utf16
+        }
+    }
+
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Encoding::Utf8 => "utf-8",
+            Encoding::Utf16 => "utf-16",
+        }
+    }
+}
+
+// Positions converts the columns of code between bytes and the encoding of the client.
+// The lines are split once, therefore a Positions should be reused for each point of a file.
+pub struct Positions<'a> {
+    code: &'a str,
+    // lines contains the byte offset of each line and its content without the line break
+    lines: Vec<(usize, &'a str)>,
+    encoding: Encoding,
+}
+
+impl<'a> Positions<'a> {
+    pub fn new(code: &'a str, encoding: Encoding) -> Self {
+        let mut lines = vec![];
+        let mut offset = 0;
+        for line in code.split('\n') {
+            lines.push((offset, line.strip_suffix('\r').unwrap_or(line)));
+            offset += line.len() + 1;
+        }
+        Self {
+            code,
+            lines,
+            encoding,
+        }
+    }
+
+    fn line(&self, row: usize) -> &'a str {
+        self.lines.get(row).map(|(_, l)| *l).unwrap_or_default()
+    }
+
+    // to_point returns the point with a byte column of a position of the client.
+    // Columns beyond the end of the line are kept as they are, columns within a char are moved
+    // to its start.
+    pub fn to_point(&self, row: usize, column: usize) -> Point {
+        let line = self.line(row);
+        if self.encoding == Encoding::Utf8 {
+            let column = match column < line.len() {
+                true => (0..=column)
+                    .rev()
+                    .find(|c| line.is_char_boundary(*c))
+                    .unwrap_or_default(),
+                false => column,
+            };
+            return Point { row, column };
+        }
+        let mut units = 0;
+        for (i, c) in line.char_indices() {
+            if units >= column {
+                return Point { row, column: i };
+            }
+            units += c.len_utf16();
+        }
+        Point {
+            row,
+            column: line.len() + column.saturating_sub(units),
+        }
+    }
+
+    // to_client returns the position of the client of a point with a byte column
+    pub fn to_client(&self, point: Point) -> Point {
+        if self.encoding == Encoding::Utf8 {
+            return point;
+        }
+        let line = self.line(point.row);
+        let column = match line.get(..point.column) {
+            Some(before) => before.encode_utf16().count(),
+            None if point.column >= line.len() => {
+                line.encode_utf16().count() + point.column - line.len()
+            }
+            // not on a char boundary, therefore the char containing the column is meant
+            None => {
+                let start = (0..point.column)
+                    .rev()
+                    .find(|c| line.is_char_boundary(*c))
+                    .unwrap_or_default();
+                line[..start].encode_utf16().count()
+            }
+        };
+        Point {
+            row: point.row,
+            column,
+        }
+    }
+
+    // to_offset returns the byte offset of a position of the client within code
+    pub fn to_offset(&self, row: usize, column: usize) -> usize {
+        match self.lines.get(row) {
+            Some((offset, line)) => offset + self.to_point(row, column).column.min(line.len()),
+            None => self.code.len(),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use tree_sitter::Point;
+
+    use super::{Encoding, Positions};
+
+    #[test]
+    fn negotiate() {
+        let supported = |s: &[&str]| s.iter().map(|s| s.to_string()).collect::<Vec<String>>();
+        assert_eq!(
+            Encoding::negotiate(&supported(&["utf-16", "utf-8"])),
+            Encoding::Utf8
+        );
+        assert_eq!(
+            Encoding::negotiate(&supported(&["utf-32"])),
+            Encoding::Utf16
+        );
+        assert_eq!(Encoding::negotiate(&[]), Encoding::Utf16);
+    }
+
+    #[test]
+    fn utf16() {
+        let code = "a = \"Jörg 😀\"; b = a;\nc = 1;";
+        let positions = Positions::new(code, Encoding::Utf16);
+        let b = code.find('b').unwrap();
+        // ö takes two bytes but one unit, 😀 four bytes but two units
+        let client = Point {
+            row: 0,
+            column: b - 3,
+        };
+        assert_eq!(
+            positions.to_point(0, client.column),
+            Point { row: 0, column: b }
+        );
+        assert_eq!(positions.to_client(Point { row: 0, column: b }), client);
+        assert_eq!(positions.to_offset(0, client.column), b);
+        assert_eq!(positions.to_offset(1, 4), code.find('1').unwrap());
+        // within ö, therefore before it
+        let o = code.find('ö').unwrap();
+        assert_eq!(
+            positions.to_client(Point {
+                row: 0,
+                column: o + 1
+            }),
+            Point { row: 0, column: o }
+        );
+        let utf8 = Positions::new(code, Encoding::Utf8);
+        assert_eq!(utf8.to_point(0, b), Point { row: 0, column: b });
+        assert_eq!(utf8.to_offset(0, b), b);
+        // within ö, therefore before it
+        assert_eq!(utf8.to_point(0, o + 1), Point { row: 0, column: o });
+        assert_eq!(utf8.to_offset(0, o + 1), o);
+        assert_eq!(utf8.to_point(1, 10), Point { row: 1, column: 10 });
+    }
+}
@@ -0,0 +1,217 @@
+use tree_sitter::{Node, Point};
+
+// Parameter is a parameter of a function; named parameters are set via `name: value`
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Parameter {
+    pub name: String,
+    pub named: bool,
+    // required is set when each caller is expected to set the parameter
+    pub required: bool,
+}
+
+impl Parameter {
+    pub fn label(&self) -> String {
+        if self.named {
+            format!("{}:", self.name)
+        } else {
+            self.name.clone()
+        }
+    }
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Signature {
+    pub name: String,
+    pub parameters: Vec<Parameter>,
+    pub documentation: Option<String>,
+}
+
+impl Signature {
+    pub fn label(&self) -> String {
+        let parameters: Vec<String> = self.parameters.iter().map(|p| p.label()).collect();
+        format!("{}({})", self.name, parameters.join(", "))
+    }
+
+    // active returns the index of the parameter the cursor of call is on.
+    // A named argument selects the parameter by name, otherwise the position is used.
+    pub fn active(&self, call: &Call) -> Option<usize> {
+        match &call.argument {
+            Some(name) => self
+                .parameters
+                .iter()
+                .position(|p| p.named && &p.name == name),
+            None => self
+                .parameters
+                .iter()
+                .enumerate()
+                .filter(|(_, p)| !p.named)
+                .nth(call.index)
+                .map(|(i, _)| i),
+        }
+    }
+}
+
+// Call describes the function call the cursor is within
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Call {
+    pub name: String,
+    // index is the position of the argument the cursor is on
+    pub index: usize,
+    // argument is the name of the named argument the cursor is on
+    pub argument: Option<String>,
+    // named contains the names of all named arguments of the call
+    pub named: Vec<String>,
+}
+
+// open_call returns the call when point is within the parentheses opened by a child of node.
+// Next to argument lists this handles ERROR nodes of unfinished calls.
+fn open_call(code: &str, node: &Node<'_>, point: Point) -> Option<Call> {
+    let crsr = &mut node.walk();
+    let children: Vec<Node<'_>> = node.children(crsr).collect();
+    let (open, name) = match node.kind() {
+        "argument_list" => {
+            let function = node.parent()?.child_by_field_name("function")?;
+            (0, function)
+        }
+        "ERROR" => {
+            let open = children
+                .iter()
+                .rposition(|c| c.kind() == "(" && c.end_position() <= point)?;
+            let function = children[..open]
+                .last()
+                .filter(|c| c.kind() == "identifier")?;
+            (open, *function)
+        }
+        _ => return None,
+    };
+    if children.get(open)?.end_position() > point {
+        return None;
+    }
+    let mut call = Call {
+        name: code[name.byte_range()].to_string(),
+        index: 0,
+        argument: None,
+        named: vec![],
+    };
+    let arguments = &children[open + 1..];
+    for (i, c) in arguments.iter().enumerate() {
+        match c.kind() {
+            ")" if !c.is_missing() && c.start_position() < point => return None,
+            ")" => break,
+            "," if c.end_position() <= point => {
+                call.index += 1;
+                call.argument = None;
+            }
+            "named_argument" => {
+                let id = c.named_child(0)?;
+                let id = code[id.byte_range()].to_string();
+                if c.start_position() < point
+                    && point <= c.end_position()
+                    && c.named_child(0)?.end_position() < point
+                {
+                    call.argument = Some(id.clone());
+                }
+                call.named.push(id);
+            }
+            // a named argument without a value is not parsed as named_argument
+            ":" if i > 0 && arguments[i - 1].kind() == "identifier" => {
+                let id = code[arguments[i - 1].byte_range()].to_string();
+                if c.end_position() <= point {
+                    call.argument = Some(id.clone());
+                }
+                call.named.push(id);
+            }
+            _ => {}
+        }
+    }
+    Some(call)
+}
+
+// call_at returns the innermost call whose arguments contain point
+pub fn call_at(code: &str, root: &Node<'_>, point: Point) -> Option<Call> {
+    // the last child starting before point may end before it when the code is incomplete
+    let mut path = vec![*root];
+    let mut node = *root;
+    loop {
+        let crsr = &mut node.walk();
+        let next = node
+            .children(crsr)
+            .filter(|c| c.start_position() < point)
+            .last();
+        match next {
+            Some(n) => {
+                path.push(n);
+                node = n;
+            }
+            None => break,
+        }
+    }
+    path.iter().rev().find_map(|n| open_call(code, n, point))
+}
+
+#[cfg(test)]
+mod tests {
+    use tree_sitter::Point;
+
+    use crate::interpret::nasl_tree;
+
+    use super::{call_at, Call, Parameter, Signature};
+
+    fn call(code: &str) -> Option<Call> {
+        let column = code.find('|').unwrap();
+        let code = code.replace('|', "");
+        let tree = nasl_tree(&code, None).unwrap();
+        call_at(&code, &tree.root_node(), Point { row: 0, column })
+    }
+
+    #[test]
+    fn calls() {
+        let expected = |name: &str, index, argument: Option<&str>, named: Vec<&str>| {
+            Some(Call {
+                name: name.to_string(),
+                index,
+                argument: argument.map(|a| a.to_string()),
+                named: named.into_iter().map(|n| n.to_string()).collect(),
+            })
+        };
+        assert_eq!(call("http_get(it|"), expected("http_get", 0, None, vec![]));
+        assert_eq!(
+            call("http_get(item: 1, |"),
+            expected("http_get", 1, None, vec!["item"])
+        );
+        assert_eq!(
+            call("a = http_get(item: 1, port: p|);"),
+            expected("http_get", 1, Some("port"), vec!["item", "port"])
+        );
+        assert_eq!(call("f(a, g(b|));"), expected("g", 0, None, vec![]));
+        assert_eq!(call("f(a, g(b), |);"), expected("f", 2, None, vec![]));
+        assert_eq!(call("f(a);|"), None);
+        assert_eq!(call("f(a)|;"), None);
+    }
+
+    #[test]
+    fn active_parameter() {
+        let signature = Signature {
+            name: "f".to_string(),
+            parameters: vec![
+                Parameter {
+                    name: "arg1".to_string(),
+                    named: false,
+                    required: false,
+                },
+                Parameter {
+                    name: "port".to_string(),
+                    named: true,
+                    required: false,
+                },
+            ],
+            documentation: None,
+        };
+        assert_eq!(signature.label(), "f(arg1, port:)");
+        let active = |code| signature.active(&call(code).unwrap());
+        assert_eq!(active("f(|"), Some(0));
+        assert_eq!(active("f(a, port: |"), Some(1));
+        assert_eq!(active("f(a, |)"), None);
+        assert_eq!(active("f(port: , |"), None);
+    }
+}
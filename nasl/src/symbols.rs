@@ -0,0 +1,262 @@
+use tree_sitter::Point;
+
+use crate::{
+    lookup::{nested_calls, Jumpable, Lookup},
+    types::{Argument, Identifier},
+    variables::{Scope, Usage},
+};
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum SymbolKind {
+    // Description is the `if (description)` block containing the meta data of a script
+    Description,
+    Function,
+    Parameter,
+    Variable,
+    Call,
+}
+
+// Symbol is an entry of the outline of a script
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Symbol {
+    pub name: String,
+    pub kind: SymbolKind,
+    pub detail: Option<String>,
+    // start and end enclose the whole symbol, e.g. a function including its body
+    pub start: Point,
+    pub end: Point,
+    // selection is the range of the name of the symbol
+    pub selection: (Point, Point),
+    pub children: Vec<Symbol>,
+}
+
+impl Symbol {
+    fn new(id: &Identifier, kind: SymbolKind) -> Option<Self> {
+        Some(Symbol {
+            name: id.identifier.clone()?,
+            kind,
+            detail: None,
+            start: id.start,
+            end: id.end,
+            selection: (id.start, id.end),
+            children: vec![],
+        })
+    }
+}
+
+fn within(outer: &Identifier, inner: &Identifier) -> bool {
+    outer.start <= inner.start && inner.end <= outer.end
+}
+
+// add adds the variable id to result unless it is already contained
+fn add(id: &Identifier, result: &mut Vec<Symbol>) {
+    if !result.iter().any(|s| id.matches(&s.name)) {
+        result.extend(Symbol::new(id, SymbolKind::Variable));
+    }
+}
+
+// variables adds the first definition of each variable within definitions and the blocks that
+// are not skipped to result
+fn variables(
+    definitions: &[Jumpable],
+    skip: &dyn Fn(&Identifier) -> bool,
+    result: &mut Vec<Symbol>,
+) {
+    for j in definitions {
+        match j {
+            Jumpable::Assign(id) | Jumpable::LocalVar(id) | Jumpable::GlobalVar(id) => {
+                add(id, result)
+            }
+            Jumpable::IfDef(_, assignments) => assignments.iter().for_each(|id| add(id, result)),
+            Jumpable::Block((id, js)) if !skip(id) => variables(&js.definitions, skip, result),
+            _ => {}
+        }
+    }
+}
+
+// declared returns true when definitions or their blocks declare name by global_var or local_var
+fn declared(definitions: &[Jumpable], name: &str, global: bool) -> bool {
+    definitions.iter().any(|j| match j {
+        Jumpable::GlobalVar(id) if global => id.matches(name),
+        Jumpable::LocalVar(id) if !global => id.matches(name),
+        Jumpable::Block((_, js)) => declared(&js.definitions, name, global),
+        _ => false,
+    })
+}
+
+// description returns the symbol of the `if (description)` block with its script_* calls
+fn description(lookup: &Lookup, top: &Scope) -> Option<Symbol> {
+    let (statement, read) = lookup.definitions.iter().find_map(|j| match j {
+        Jumpable::IfDef(statement, _) => top
+            .variables
+            .iter()
+            .find(|v| v.usage == Usage::Read && v.id.matches("description"))
+            .filter(|v| within(statement, &v.id))
+            .map(|v| (statement, &v.id)),
+        _ => None,
+    })?;
+    let block = lookup.definitions.iter().find_map(|j| match j {
+        Jumpable::Block((id, js)) if within(statement, id) && id.start >= read.end => Some(js),
+        _ => None,
+    })?;
+    let children = nested_calls(block)
+        .filter_map(|j| match j {
+            Jumpable::CallExpression(id, arguments)
+                if id.identifier.as_ref()?.starts_with("script_") =>
+            {
+                let mut symbol = Symbol::new(id, SymbolKind::Call)?;
+                symbol.detail = arguments.iter().find_map(|a| match a {
+                    Argument::StringLiteral(_) => a.to_string(),
+                    _ => None,
+                });
+                Some(symbol)
+            }
+            _ => None,
+        })
+        .collect();
+    Some(Symbol {
+        name: "description".to_string(),
+        kind: SymbolKind::Description,
+        detail: None,
+        start: statement.start,
+        end: statement.end,
+        selection: (read.start, read.end),
+        children,
+    })
+}
+
+// outline returns the description block, the functions with their parameters and local
+// variables as well as the global variables of the top level code of a script
+pub fn outline(lookup: &Lookup, scopes: &[Scope]) -> Vec<Symbol> {
+    let functions: Vec<(&Identifier, &Vec<Identifier>)> = lookup
+        .definitions
+        .iter()
+        .filter_map(|j| match j {
+            Jumpable::FunDef(id, parameters) => Some((id, parameters)),
+            _ => None,
+        })
+        .collect();
+    let in_function = |id: &Identifier| functions.iter().any(|(f, _)| within(f, id));
+    let mut result = vec![];
+    let description = scopes
+        .iter()
+        .find(|s| s.function.is_none())
+        .and_then(|top| description(lookup, top));
+    let in_description = |id: &Identifier| {
+        description
+            .as_ref()
+            .is_some_and(|d| d.start <= id.start && id.end <= d.end)
+    };
+    let mut globals = vec![];
+    variables(
+        &lookup.definitions,
+        &|id| in_function(id) || in_description(id),
+        &mut globals,
+    );
+    for (id, parameters) in &functions {
+        let mut symbol = match Symbol::new(id, SymbolKind::Function) {
+            Some(s) => s,
+            None => continue,
+        };
+        let scope = scopes
+            .iter()
+            .find_map(|s| s.function.as_ref().filter(|f| within(id, f)));
+        if let Some(name) = scope {
+            symbol.selection = (name.start, name.end);
+        }
+        let names: Vec<String> = parameters
+            .iter()
+            .filter_map(|p| p.identifier.clone())
+            .collect();
+        symbol.detail = Some(format!("({})", names.join(", ")));
+        symbol.children = parameters
+            .iter()
+            .filter_map(|p| Symbol::new(p, SymbolKind::Parameter))
+            .collect();
+        let body: Vec<Jumpable> = lookup
+            .definitions
+            .iter()
+            .filter(|j| matches!(j, Jumpable::Block((b, _)) if within(id, b)))
+            .cloned()
+            .collect();
+        let mut locals = vec![];
+        variables(&body, &|_| false, &mut locals);
+        // assignments to global variables are no local variables
+        symbol.children.extend(locals.into_iter().filter(|l| {
+            !names.contains(&l.name)
+                && (declared(&body, &l.name, false)
+                    || !declared(&body, &l.name, true) && !globals.iter().any(|g| g.name == l.name))
+        }));
+        result.push(symbol);
+    }
+    result.extend(description);
+    result.extend(globals);
+    result.sort_by_key(|s| s.start);
+    result
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::interpret::NASLInterpreter;
+
+    use super::{Symbol, SymbolKind};
+
+    fn names(symbols: &[Symbol]) -> Vec<(String, SymbolKind)> {
+        symbols
+            .iter()
+            .map(|s| (s.name.clone(), s.kind.clone()))
+            .collect()
+    }
+
+    #[test]
+    fn outline() {
+        let code = r#"
+if (description) {
+  script_oid("1.3.6.1.4.1.25623.1.0.1");
+  script_name("Example");
+  exit(0);
+}
+port = 80;
+function f(a) {
+  local_var l;
+  global_var g;
+  l = a;
+  g = 1;
+  port = 2;
+  x = 3;
+}
+if (port) {
+  banner = "b";
+}
+"#;
+        let js = NASLInterpreter::new("aha.nasl", code).unwrap();
+        let symbols = js.symbols();
+        assert_eq!(
+            names(&symbols),
+            vec![
+                ("description".to_string(), SymbolKind::Description),
+                ("port".to_string(), SymbolKind::Variable),
+                ("f".to_string(), SymbolKind::Function),
+                ("banner".to_string(), SymbolKind::Variable),
+            ]
+        );
+        assert_eq!(
+            names(&symbols[0].children),
+            vec![
+                ("script_oid".to_string(), SymbolKind::Call),
+                ("script_name".to_string(), SymbolKind::Call),
+            ]
+        );
+        assert_eq!(symbols[0].children[1].detail, Some("Example".to_string()));
+        assert_eq!(
+            names(&symbols[2].children),
+            vec![
+                ("a".to_string(), SymbolKind::Parameter),
+                ("l".to_string(), SymbolKind::Variable),
+                ("x".to_string(), SymbolKind::Variable),
+            ]
+        );
+        assert_eq!(symbols[2].detail, Some("(a)".to_string()));
+        assert_eq!(symbols[2].selection.0.column, 9);
+    }
+}
@@ -7,33 +7,45 @@ pub struct Identifier {
     pub identifier: Option<String>,
 }
 
-pub fn to_pos(r: usize, c: usize) -> f32 {
-    r as f32 + c as f32 / 100.0
+// Definition is a named definition that is visible at a position
+#[derive(Clone, Debug, PartialEq)]
+pub enum Definition {
+    Function(Identifier, Vec<Identifier>),
+    Variable(Identifier),
 }
+
+impl Definition {
+    pub fn identifier(&self) -> &Identifier {
+        match self {
+            Definition::Function(id, _) => id,
+            Definition::Variable(id) => id,
+        }
+    }
+}
+
 #[derive(Clone, Debug)]
 pub enum Argument {
     StringLiteral(Identifier),
+    // Named is the name of a named argument
+    Named(Identifier),
+    // Positional is any other unnamed argument
+    Positional(Identifier),
 }
 
 impl Argument {
     pub fn to_string(&self) -> Option<String> {
         match self {
             Argument::StringLiteral(id) => id.clone().identifier,
+            _ => None,
         }
     }
 }
 
 impl Identifier {
-    pub fn as_pos(&self) -> (f32, f32) {
-        (
-            to_pos(self.start.row, self.start.column),
-            to_pos(self.end.row, self.end.column),
-        )
-    }
-
-    pub fn in_pos(&self, pos: f32) -> bool {
-        let (start, end) = self.as_pos();
-        pos >= start && pos <= end
+    // in_pos returns true when pos is within the identifier; points are ordered by row and
+    // then by column
+    pub fn in_pos(&self, pos: Point) -> bool {
+        pos >= self.start && pos <= self.end
     }
 
     pub fn matches(&self, name: &str) -> bool {
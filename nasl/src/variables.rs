@@ -0,0 +1,515 @@
+use tree_sitter::{Node, Point};
+
+use crate::types::Identifier;
+
+// RUNTIME_VARIABLES are set by the interpreter when a script or a function is executed
+pub const RUNTIME_VARIABLES: [&str; 3] = ["description", "_FCT_ANON_ARGS", "SCRIPT_NAME"];
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum Usage {
+    Read,
+    Write,
+    // Local is a local_var declaration
+    Local,
+    // Global is a global_var declaration
+    Global,
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct Variable {
+    pub id: Identifier,
+    pub usage: Usage,
+    // removable is the range of the statement that only sets the variable without calling a
+    // function; it can be removed without side effects
+    pub removable: Option<(Point, Point)>,
+}
+
+// Scope contains the variable usages of the top level code or of a function in the order they
+// appear in code
+#[derive(Clone, Debug, PartialEq)]
+pub struct Scope {
+    // function is the name of the function; top level code has none
+    pub function: Option<Identifier>,
+    pub parameters: Vec<Identifier>,
+    pub variables: Vec<Variable>,
+    // loops contains the range of the body of each loop within the scope
+    pub loops: Vec<(Point, Point)>,
+}
+
+impl Scope {
+    fn new(function: Option<Identifier>, parameters: Vec<Identifier>) -> Self {
+        Scope {
+            function,
+            parameters,
+            variables: vec![],
+            loops: vec![],
+        }
+    }
+
+    fn push(&mut self, code: &str, node: &Node<'_>, usage: Usage) {
+        self.variables.push(Variable {
+            id: Identifier {
+                start: node.start_position(),
+                end: node.end_position(),
+                identifier: Some(code[node.byte_range()].to_string()),
+            },
+            usage,
+            removable: None,
+        });
+    }
+
+    // removable marks the last usage as removable by the statement node when value has no side
+    // effects
+    fn removable(&mut self, node: &Node<'_>, value: Option<Node<'_>>) {
+        if value.is_some_and(|v| has_side_effects(&v)) {
+            return;
+        }
+        if let Some(v) = self.variables.last_mut() {
+            v.removable = Some((node.start_position(), node.end_position()));
+        }
+    }
+
+    // assigned_in_loop returns true when the read v is within the body of a loop that assigns it
+    // as well; the value is then carried over from a previous iteration
+    fn assigned_in_loop(&self, v: &Variable) -> bool {
+        let within =
+            |(start, end): &(Point, Point), id: &Identifier| *start <= id.start && id.end <= *end;
+        self.loops.iter().any(|l| {
+            within(l, &v.id)
+                && self.variables.iter().any(|w| {
+                    w.usage != Usage::Read && w.id.identifier == v.id.identifier && within(l, &w.id)
+                })
+        })
+    }
+
+    // writes returns the names of the variables assigned or declared within the scope
+    pub fn writes(&self) -> impl Iterator<Item = &str> + '_ {
+        self.variables
+            .iter()
+            .filter(|v| v.usage != Usage::Read)
+            .filter_map(|v| v.id.identifier.as_deref())
+    }
+
+    // globals returns the names of the variables declared by global_var
+    pub fn globals(&self) -> impl Iterator<Item = &str> + '_ {
+        self.variables
+            .iter()
+            .filter(|v| v.usage == Usage::Global)
+            .filter_map(|v| v.id.identifier.as_deref())
+    }
+}
+
+// has_side_effects returns true when node calls a function or assigns a variable
+fn has_side_effects(node: &Node<'_>) -> bool {
+    if matches!(
+        node.kind(),
+        "call_expression" | "assignment_expression" | "update_expression"
+    ) {
+        return true;
+    }
+    let crsr = &mut node.walk();
+    let result = node.named_children(crsr).any(|c| has_side_effects(&c));
+    result
+}
+
+// walk records the variable usages of node within scope; functions are added to scopes
+fn walk(code: &str, node: &Node<'_>, scope: &mut Scope, scopes: &mut Vec<Scope>) {
+    if matches!(
+        node.kind(),
+        "for_statement" | "foreach_statement" | "while_statement" | "repeat_statement"
+    ) {
+        if let Some(body) = node.child_by_field_name("body") {
+            scope
+                .loops
+                .push((body.start_position(), body.end_position()));
+        }
+    }
+    match node.kind() {
+        "identifier" => scope.push(code, node, Usage::Read),
+        "ERROR" | "function_declarator" | "parameter_list" => {}
+        "function_definition" => {
+            let declarator = node.child_by_field_name("declarator");
+            let parameters = declarator
+                .and_then(|d| d.child_by_field_name("parameters"))
+                .map(|p| {
+                    let crsr = &mut p.walk();
+                    p.named_children(crsr)
+                        .filter(|c| c.kind() == "identifier")
+                        .map(|c| Identifier {
+                            start: c.start_position(),
+                            end: c.end_position(),
+                            identifier: Some(code[c.byte_range()].to_string()),
+                        })
+                        .collect()
+                })
+                .unwrap_or_default();
+            let name = declarator
+                .and_then(|d| d.child_by_field_name("declarator"))
+                .map(|n| Identifier {
+                    start: n.start_position(),
+                    end: n.end_position(),
+                    identifier: Some(code[n.byte_range()].to_string()),
+                });
+            let mut function = Scope::new(name, parameters);
+            if let Some(body) = node.child_by_field_name("body") {
+                walk(code, &body, &mut function, scopes);
+            }
+            scopes.push(function);
+        }
+        "assignment_expression" => {
+            if let Some(right) = node.child_by_field_name("right") {
+                walk(code, &right, scope, scopes);
+            }
+            let compound = node
+                .child_by_field_name("operator")
+                .map(|o| &code[o.byte_range()] != "=")
+                .unwrap_or_default();
+            if let Some(left) = node.child_by_field_name("left") {
+                assign(code, &left, compound, scope, scopes);
+                let statement = node.parent().filter(|p| p.kind() == "expression_statement");
+                if let Some(statement) =
+                    statement.filter(|_| !compound && left.kind() == "identifier")
+                {
+                    scope.removable(&statement, node.child_by_field_name("right"));
+                }
+            }
+        }
+        "update_expression" => {
+            if let Some(argument) = node.child_by_field_name("argument") {
+                assign(code, &argument, true, scope, scopes);
+            }
+        }
+        "declaration" => {
+            let usage = match node.child_by_field_name("type") {
+                Some(t) if &code[t.byte_range()] == "global_var" => Usage::Global,
+                _ => Usage::Local,
+            };
+            let crsr = &mut node.walk();
+            let declarators: Vec<Node<'_>> =
+                node.children_by_field_name("declarator", crsr).collect();
+            for d in &declarators {
+                match d.kind() {
+                    "identifier" => scope.push(code, d, usage.clone()),
+                    "init_declarator" => {
+                        let value = d.child_by_field_name("value");
+                        if let Some(value) = value {
+                            walk(code, &value, scope, scopes);
+                        }
+                        if let Some(id) = d
+                            .child_by_field_name("declarator")
+                            .filter(|i| i.kind() == "identifier")
+                        {
+                            scope.push(code, &id, usage.clone());
+                        }
+                    }
+                    _ => {}
+                }
+            }
+            // a declaration of multiple variables is kept as it is
+            if let [d] = declarators[..] {
+                if usage == Usage::Local {
+                    scope.removable(node, d.child_by_field_name("value"));
+                }
+            }
+        }
+        "foreach_statement" => {
+            if let Some(selection) = node.child_by_field_name("selection") {
+                walk(code, &selection, scope, scopes);
+            }
+            if let Some(element) = node.child_by_field_name("element") {
+                scope.push(code, &element, Usage::Write);
+            }
+            if let Some(body) = node.child_by_field_name("body") {
+                walk(code, &body, scope, scopes);
+            }
+        }
+        "call_expression" => {
+            // the function name is no variable
+            if let Some(arguments) = node.child_by_field_name("arguments") {
+                walk(code, &arguments, scope, scopes);
+            }
+        }
+        "named_argument" => {
+            let crsr = &mut node.walk();
+            for c in node.named_children(crsr).skip(1) {
+                walk(code, &c, scope, scopes);
+            }
+        }
+        "field_expression" => {
+            if let Some(argument) = node.child_by_field_name("argument") {
+                walk(code, &argument, scope, scopes);
+            }
+        }
+        _ => {
+            let crsr = &mut node.walk();
+            for c in node.named_children(crsr) {
+                walk(code, &c, scope, scopes);
+            }
+        }
+    }
+}
+
+// assign records the assignment to node. A compound assignment reads the variable before.
+// Assigning to an element of an array creates the array when necessary.
+fn assign(code: &str, node: &Node<'_>, compound: bool, scope: &mut Scope, scopes: &mut Vec<Scope>) {
+    match node.kind() {
+        "identifier" => {
+            if compound {
+                scope.push(code, node, Usage::Read);
+            }
+            scope.push(code, node, Usage::Write);
+        }
+        "subscript_expression" => {
+            if let Some(index) = node.child_by_field_name("index") {
+                walk(code, &index, scope, scopes);
+            }
+            if let Some(argument) = node.child_by_field_name("argument") {
+                assign(code, &argument, compound, scope, scopes);
+            }
+        }
+        _ => walk(code, node, scope, scopes),
+    }
+}
+
+// scopes returns the scope of the top level code followed by the scope of each function
+pub fn scopes(code: &str, root: &Node<'_>) -> Vec<Scope> {
+    let mut top = Scope::new(None, vec![]);
+    let mut functions = vec![];
+    walk(code, root, &mut top, &mut functions);
+    let mut result = vec![top];
+    result.extend(functions);
+    result
+}
+
+// undefined_variables returns each read of a variable that is not assigned before.
+// Within the top level code a variable has to be assigned earlier. Within a function it has to
+// be a parameter, assigned anywhere within the function, declared by global_var or assigned
+// within the top level code. known contains the variables defined elsewhere, e.g. by includes.
+pub fn undefined_variables(scopes: &[Scope], known: &dyn Fn(&str) -> bool) -> Vec<Identifier> {
+    let defined = |name: &str| known(name) || RUNTIME_VARIABLES.contains(&name);
+    let mut globals: Vec<&str> = scopes.iter().flat_map(|s| s.globals()).collect();
+    let mut result = vec![];
+    if let Some(top) = scopes.first().filter(|s| s.function.is_none()) {
+        let mut assigned: Vec<&str> = vec![];
+        for v in &top.variables {
+            let name = v.id.identifier.as_deref().unwrap_or_default();
+            match v.usage {
+                Usage::Read
+                    if !defined(name) && !assigned.contains(&name) && !top.assigned_in_loop(v) =>
+                {
+                    result.push(v.id.clone())
+                }
+                Usage::Read => {}
+                _ => assigned.push(name),
+            }
+        }
+        globals.extend(top.writes());
+    }
+    for scope in scopes.iter().filter(|s| s.function.is_some()) {
+        let locals: Vec<&str> = scope.writes().collect();
+        result.extend(
+            scope
+                .variables
+                .iter()
+                .filter(|v| v.usage == Usage::Read)
+                .filter(|v| {
+                    let name = v.id.identifier.as_deref().unwrap_or_default();
+                    !defined(name)
+                        && !locals.contains(&name)
+                        && !globals.contains(&name)
+                        && !scope.parameters.iter().any(|p| p.matches(name))
+                })
+                .map(|v| v.id.clone()),
+        );
+    }
+    result.sort_by_key(|id| id.start);
+    result
+}
+
+// is_global returns true when a write to name within scope changes a global variable. This is the
+// case when name is declared by global_var, assigned within the top level code or a global of an
+// include and not declared by local_var within scope.
+fn is_global(scopes: &[Scope], scope: &Scope, name: &str, included: &dyn Fn(&str) -> bool) -> bool {
+    let declared = |usage: Usage| {
+        scope
+            .variables
+            .iter()
+            .any(|v| v.usage == usage && v.id.matches(name))
+    };
+    if declared(Usage::Local) {
+        return false;
+    }
+    declared(Usage::Global)
+        || included(name)
+        || scopes
+            .iter()
+            .filter(|s| s.function.is_none())
+            .any(|s| s.writes().any(|w| w == name))
+}
+
+// Unused is a local variable of a function that is never read within that function
+#[derive(Clone, Debug, PartialEq)]
+pub struct Unused<'a> {
+    // usages contains each declaration and write of the variable in the order they appear in code
+    pub usages: Vec<&'a Variable>,
+}
+
+impl Unused<'_> {
+    // first returns the declaration of the variable or, without one, its first write
+    pub fn first(&self) -> &Variable {
+        self.usages
+            .iter()
+            .find(|v| v.usage == Usage::Local)
+            .unwrap_or(&self.usages[0])
+    }
+}
+
+// unused_variables returns each local variable within a function that is never read within that
+// function
+pub fn unused_variables<'a>(
+    scopes: &'a [Scope],
+    included: &dyn Fn(&str) -> bool,
+) -> Vec<Unused<'a>> {
+    let mut result: Vec<Unused<'_>> = vec![];
+    for scope in scopes.iter().filter(|s| s.function.is_some()) {
+        let read = |name: &str| {
+            scope
+                .variables
+                .iter()
+                .any(|v| v.usage == Usage::Read && v.id.matches(name))
+        };
+        let start = result.len();
+        for v in scope.variables.iter().filter(|v| {
+            let name = v.id.identifier.as_deref().unwrap_or_default();
+            matches!(v.usage, Usage::Write | Usage::Local)
+                && !read(name)
+                && !scope.parameters.iter().any(|p| p.matches(name))
+                && !is_global(scopes, scope, name, included)
+        }) {
+            match result[start..]
+                .iter_mut()
+                .find(|u| u.usages[0].id.identifier == v.id.identifier)
+            {
+                Some(u) => u.usages.push(v),
+                None => result.push(Unused { usages: vec![v] }),
+            }
+        }
+    }
+    result
+}
+
+// unused_parameters returns each parameter of a function that is never read within that function
+pub fn unused_parameters(scopes: &[Scope]) -> Vec<&Identifier> {
+    scopes
+        .iter()
+        .flat_map(|s| {
+            s.parameters.iter().filter(|p| {
+                !s.variables
+                    .iter()
+                    .any(|v| v.usage == Usage::Read && v.id.identifier == p.identifier)
+            })
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use tree_sitter::Point;
+
+    use crate::interpret::nasl_tree;
+
+    use super::{scopes, undefined_variables, unused_parameters, unused_variables, Usage};
+
+    fn undefined(code: &str) -> Vec<String> {
+        let tree = nasl_tree(code, None).unwrap();
+        let scopes = scopes(code, &tree.root_node());
+        undefined_variables(&scopes, &|n| n == "known")
+            .into_iter()
+            .filter_map(|id| id.identifier)
+            .collect()
+    }
+
+    #[test]
+    fn top_level() {
+        assert_eq!(undefined("port = 80;\ndisplay(prot);"), vec!["prot"]);
+        assert_eq!(undefined("display(port);\nport = 80;"), vec!["port"]);
+        assert_eq!(
+            undefined("if (description) { a = known; }\na += 1;"),
+            Vec::<String>::new()
+        );
+        assert_eq!(undefined("i++;\nx[j] = 1;"), vec!["i", "j"]);
+        assert_eq!(
+            undefined("foreach p (ports) { display(p); }\nfor (i = 0; i < 2; i++) {}"),
+            vec!["ports"]
+        );
+        // a value carried over from a previous iteration of a loop
+        assert_eq!(
+            undefined("i = 0;\nwhile (i < 3) { if (i > 0) display(prev); prev = i; i++; }"),
+            Vec::<String>::new()
+        );
+        assert_eq!(
+            undefined("foreach p (ports) { display(last); }\nlast = 1;"),
+            vec!["ports", "last"]
+        );
+    }
+
+    #[test]
+    fn functions() {
+        let code = r#"
+function f(port) {
+  local_var l;
+  global_var g;
+  l = port + g + later + x;
+  return f(a: undefined) + y;
+}
+later = 1;
+y = 2;
+"#;
+        assert_eq!(undefined(code), vec!["x", "undefined"]);
+    }
+
+    #[test]
+    fn unused() {
+        let code = r#"
+function f(port, unused) {
+  local_var a, b, g;
+  global_var c;
+  a = 1;
+  b = g = port;
+  c = 2;
+  d = recv(socket: port);
+  e = 3;
+  t = 4;
+  return a;
+}
+t = 0;
+"#;
+        let tree = nasl_tree(code, None).unwrap();
+        let scopes = scopes(code, &tree.root_node());
+        let unused = unused_variables(&scopes, &|_| false);
+        let names: Vec<&str> = unused
+            .iter()
+            .filter_map(|u| u.first().id.identifier.as_deref())
+            .collect();
+        assert_eq!(names, vec!["b", "g", "d", "e"]);
+        // each variable is reported once at its declaration
+        assert_eq!(unused[0].usages.len(), 2);
+        assert_eq!(unused[0].first().usage, Usage::Local);
+        // neither declarations of multiple variables nor assignments with side effects
+        assert!(unused[..3]
+            .iter()
+            .flat_map(|u| &u.usages)
+            .all(|v| v.removable.is_none()));
+        assert_eq!(
+            unused[3].first().removable,
+            Some((Point { row: 8, column: 2 }, Point { row: 8, column: 8 }))
+        );
+        assert_eq!(
+            unused_parameters(&scopes)
+                .into_iter()
+                .filter_map(|p| p.identifier.clone())
+                .collect::<Vec<String>>(),
+            vec!["unused"]
+        );
+    }
+}
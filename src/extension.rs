@@ -1,4 +1,22 @@
-use lsp_types::{Position, Range};
+use std::collections::HashMap;
+
+use lsp_types::{
+    CodeAction, CodeActionKind, CompletionItem, CompletionItemKind, CompletionTextEdit,
+    DiagnosticSeverity, DocumentSymbol, Location, MarkupContent, MarkupKind, ParameterInformation,
+    ParameterLabel, Position, Range, SignatureInformation, SymbolInformation,
+    SymbolKind as LspSymbolKind, TextEdit, Url, WorkspaceEdit,
+};
+use nasl::{
+    actions::{Action, Edit},
+    completion::{Completion, CompletionKind},
+    diagnostics::{Diagnostic, Severity},
+    index::{IndexKind, Indexed},
+    openvas_funcs::Builtin,
+    position::Positions,
+    signature::{Call, Signature},
+    symbols::{Symbol, SymbolKind},
+    types::Identifier,
+};
 use serde::{Deserialize, Serialize};
 use tree_sitter::Point;
 
@@ -7,6 +25,8 @@ use tree_sitter::Point;
 pub struct Paths {
     pub paths: Option<Vec<String>>,
     pub openvas: Option<String>,
+    // cache_size is the maximal amount of parsed scripts that are kept in memory
+    pub cache_size: Option<usize>,
 }
 
 #[derive(Debug, PartialEq, Clone, Deserialize, Serialize)]
@@ -33,3 +53,270 @@ impl AsRangeExt for Point {
         }
     }
 }
+
+fn range(start: &Point, end: &Point) -> Range {
+    Range {
+        start: Position {
+            line: start.row as u32,
+            character: start.column as u32,
+        },
+        end: Position {
+            line: end.row as u32,
+            character: end.column as u32,
+        },
+    }
+}
+
+impl AsRangeExt for Identifier {
+    fn as_range(&self) -> Range {
+        range(&self.start, &self.end)
+    }
+}
+
+impl AsRangeExt for Diagnostic {
+    fn as_range(&self) -> Range {
+        range(&self.start, &self.end)
+    }
+}
+
+pub trait AsDiagnosticExt {
+    fn as_diagnostic(&self) -> lsp_types::Diagnostic;
+}
+
+impl AsDiagnosticExt for Diagnostic {
+    fn as_diagnostic(&self) -> lsp_types::Diagnostic {
+        lsp_types::Diagnostic {
+            range: self.as_range(),
+            severity: Some(match self.severity {
+                Severity::Error => DiagnosticSeverity::ERROR,
+                Severity::Warning => DiagnosticSeverity::WARNING,
+                Severity::Information => DiagnosticSeverity::INFORMATION,
+                Severity::Hint => DiagnosticSeverity::HINT,
+            }),
+            source: Some("nasl-analyzer".to_string()),
+            message: self.message.clone(),
+            ..Default::default()
+        }
+    }
+}
+
+// ToClientExt converts the byte columns of a document into the position encoding of the client
+pub trait ToClientExt {
+    fn to_client(&self, positions: &Positions<'_>) -> Self;
+}
+
+impl ToClientExt for Diagnostic {
+    fn to_client(&self, positions: &Positions<'_>) -> Self {
+        Diagnostic {
+            start: positions.to_client(self.start),
+            end: positions.to_client(self.end),
+            ..self.clone()
+        }
+    }
+}
+
+impl ToClientExt for Completion {
+    fn to_client(&self, positions: &Positions<'_>) -> Self {
+        Completion {
+            replace: self
+                .replace
+                .map(|(start, end)| (positions.to_client(start), positions.to_client(end))),
+            ..self.clone()
+        }
+    }
+}
+
+impl ToClientExt for Action {
+    fn to_client(&self, positions: &Positions<'_>) -> Self {
+        Action {
+            title: self.title.clone(),
+            diagnostic: self.diagnostic.to_client(positions),
+            edits: self
+                .edits
+                .iter()
+                .map(|e| Edit {
+                    start: positions.to_client(e.start),
+                    end: positions.to_client(e.end),
+                    text: e.text.clone(),
+                })
+                .collect(),
+        }
+    }
+}
+
+impl ToClientExt for Symbol {
+    fn to_client(&self, positions: &Positions<'_>) -> Self {
+        Symbol {
+            start: positions.to_client(self.start),
+            end: positions.to_client(self.end),
+            selection: (
+                positions.to_client(self.selection.0),
+                positions.to_client(self.selection.1),
+            ),
+            children: self
+                .children
+                .iter()
+                .map(|c| c.to_client(positions))
+                .collect(),
+            ..self.clone()
+        }
+    }
+}
+
+pub trait AsCompletionItemExt {
+    fn as_completion_item(&self) -> CompletionItem;
+}
+
+impl AsCompletionItemExt for Completion {
+    fn as_completion_item(&self) -> CompletionItem {
+        CompletionItem {
+            label: self.label.clone(),
+            kind: Some(match self.kind {
+                CompletionKind::Builtin | CompletionKind::Function => CompletionItemKind::FUNCTION,
+                CompletionKind::Variable => CompletionItemKind::VARIABLE,
+                CompletionKind::Include => CompletionItemKind::FILE,
+                CompletionKind::Argument => CompletionItemKind::FIELD,
+            }),
+            detail: self.detail.clone(),
+            text_edit: self.replace.map(|(start, end)| {
+                CompletionTextEdit::Edit(TextEdit {
+                    range: range(&start, &end),
+                    new_text: self.label.clone(),
+                })
+            }),
+            ..Default::default()
+        }
+    }
+}
+
+pub trait AsMarkupExt {
+    fn as_markup(&self) -> MarkupContent;
+}
+
+impl AsMarkupExt for Builtin {
+    fn as_markup(&self) -> MarkupContent {
+        let mut value = format!(
+            "```nasl\n{}\n```\n\nbuiltin implemented by `{}`",
+            self.signature().label(),
+            self.function
+        );
+        if let Some((path, point)) = &self.source {
+            value.push_str(&format!(" in `{}:{}`", path, point.row + 1));
+        }
+        if let Some(documentation) = &self.documentation {
+            value.push_str(&format!("\n\n---\n\n{documentation}"));
+        }
+        MarkupContent {
+            kind: MarkupKind::Markdown,
+            value,
+        }
+    }
+}
+
+pub trait AsSignatureInformationExt {
+    fn as_signature_information(&self, call: &Call) -> SignatureInformation;
+}
+
+impl AsSignatureInformationExt for Signature {
+    fn as_signature_information(&self, call: &Call) -> SignatureInformation {
+        SignatureInformation {
+            label: self.label(),
+            documentation: self.documentation.as_ref().map(|d| {
+                lsp_types::Documentation::MarkupContent(MarkupContent {
+                    kind: MarkupKind::Markdown,
+                    value: d.clone(),
+                })
+            }),
+            parameters: Some(
+                self.parameters
+                    .iter()
+                    .map(|p| ParameterInformation {
+                        label: ParameterLabel::Simple(p.label()),
+                        documentation: None,
+                    })
+                    .collect(),
+            ),
+            active_parameter: self.active(call).map(|i| i as u32),
+        }
+    }
+}
+
+pub trait AsCodeActionExt {
+    fn as_code_action(&self, uri: &Url) -> CodeAction;
+}
+
+impl AsCodeActionExt for Action {
+    fn as_code_action(&self, uri: &Url) -> CodeAction {
+        let edits = self
+            .edits
+            .iter()
+            .map(|e| TextEdit {
+                range: range(&e.start, &e.end),
+                new_text: e.text.clone(),
+            })
+            .collect();
+        CodeAction {
+            title: self.title.clone(),
+            kind: Some(CodeActionKind::QUICKFIX),
+            diagnostics: Some(vec![self.diagnostic.as_diagnostic()]),
+            edit: Some(WorkspaceEdit {
+                changes: Some(HashMap::from([(uri.clone(), edits)])),
+                ..Default::default()
+            }),
+            ..Default::default()
+        }
+    }
+}
+
+pub trait AsDocumentSymbolExt {
+    fn as_document_symbol(&self) -> DocumentSymbol;
+}
+
+impl AsDocumentSymbolExt for Symbol {
+    fn as_document_symbol(&self) -> DocumentSymbol {
+        // deprecated is replaced by tags but has to be set nevertheless
+        #[allow(deprecated)]
+        DocumentSymbol {
+            name: self.name.clone(),
+            detail: self.detail.clone(),
+            kind: match self.kind {
+                SymbolKind::Description => LspSymbolKind::NAMESPACE,
+                SymbolKind::Function => LspSymbolKind::FUNCTION,
+                SymbolKind::Parameter | SymbolKind::Variable => LspSymbolKind::VARIABLE,
+                SymbolKind::Call => LspSymbolKind::PROPERTY,
+            },
+            tags: None,
+            deprecated: None,
+            range: range(&self.start, &self.end),
+            selection_range: range(&self.selection.0, &self.selection.1),
+            children: Some(
+                self.children
+                    .iter()
+                    .map(|c| c.as_document_symbol())
+                    .collect(),
+            ),
+        }
+    }
+}
+
+pub trait AsSymbolInformationExt {
+    fn as_symbol_information(&self, location: Location) -> SymbolInformation;
+}
+
+impl AsSymbolInformationExt for Indexed {
+    fn as_symbol_information(&self, location: Location) -> SymbolInformation {
+        #[allow(deprecated)]
+        SymbolInformation {
+            name: self.name().to_string(),
+            kind: match self.kind {
+                IndexKind::Function => LspSymbolKind::FUNCTION,
+                IndexKind::Oid => LspSymbolKind::KEY,
+                IndexKind::Name => LspSymbolKind::FILE,
+            },
+            tags: None,
+            deprecated: None,
+            location,
+            container_name: self.container.clone(),
+        }
+    }
+}
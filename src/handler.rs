@@ -1,20 +1,75 @@
-use std::{error::Error, str::FromStr};
+use std::{collections::HashMap, error::Error, str::FromStr};
 
-use lsp_server::{Connection, Message, RequestId, Response};
+use lsp_server::{Connection, Message, Notification, Request, RequestId, Response};
 use nasl::{
     cache::Cache,
-    interpret::NASLInterpreter,
+    interpret::{NASLInterpreter, SearchParameter},
+    position::{Encoding, Positions},
+    types::Identifier,
 };
 
-use lsp_types::{GotoDefinitionParams, GotoDefinitionResponse, Location, Url};
+use lsp_types::{
+    notification::{DidChangeWatchedFiles, Notification as _, PublishDiagnostics},
+    request::RegisterCapability,
+    CodeActionOrCommand, CodeActionParams, CodeActionResponse, CompletionParams,
+    CompletionResponse, Diagnostic, DidChangeTextDocumentParams, DidChangeWatchedFilesParams,
+    DidChangeWatchedFilesRegistrationOptions, DidCloseTextDocumentParams,
+    DidOpenTextDocumentParams, DidSaveTextDocumentParams, DocumentSymbol, DocumentSymbolParams,
+    DocumentSymbolResponse, ExecuteCommandParams, FileSystemWatcher, GotoDefinitionParams,
+    GotoDefinitionResponse, Hover, HoverContents, HoverParams, Location, MarkupContent, MarkupKind,
+    Position, PublishDiagnosticsParams, ReferenceParams, Registration, RegistrationParams,
+    SignatureHelp, SignatureHelpParams, SymbolInformation, Url, WorkspaceSymbolParams,
+};
 use tracing::{debug, warn};
 use tree_sitter::Point;
 
-use crate::extension::AsRangeExt;
+use crate::extension::{
+    AsCodeActionExt, AsCompletionItemExt, AsDiagnosticExt, AsDocumentSymbolExt, AsMarkupExt,
+    AsRangeExt, AsSignatureInformationExt, AsSymbolInformationExt, ToClientExt,
+};
 
 pub trait ToResponseExt<T, R> {
     fn handle(&mut self, params: T) -> Option<R>;
 }
+
+// CACHE_STATISTICS is the command to get the statistics of the interpreter cache
+pub const CACHE_STATISTICS: &str = "nasl-analyzer.cacheStatistics";
+
+// WATCH_FILES is the id of the registration of the file watcher
+const WATCH_FILES: &str = "nasl-analyzer.watchFiles";
+
+pub trait NotificationExt<T> {
+    fn notify(&mut self, params: T);
+}
+
+// TextDocumentExt returns the uri of the document a notification is about
+pub trait TextDocumentExt {
+    fn uri(&self) -> &Url;
+}
+
+impl TextDocumentExt for DidOpenTextDocumentParams {
+    fn uri(&self) -> &Url {
+        &self.text_document.uri
+    }
+}
+
+impl TextDocumentExt for DidChangeTextDocumentParams {
+    fn uri(&self) -> &Url {
+        &self.text_document.uri
+    }
+}
+
+impl TextDocumentExt for DidSaveTextDocumentParams {
+    fn uri(&self) -> &Url {
+        &self.text_document.uri
+    }
+}
+
+impl TextDocumentExt for DidCloseTextDocumentParams {
+    fn uri(&self) -> &Url {
+        &self.text_document.uri
+    }
+}
 pub struct RequestResponseSender<'a> {
     pub connection: &'a Connection,
 }
@@ -38,16 +93,118 @@ impl<'a> RequestResponseSender<'a> {
         self.connection.sender.send(Message::Response(resp))?;
         Ok(())
     }
+
+    pub fn send_notification<N>(
+        &self,
+        params: N::Params,
+    ) -> Result<(), Box<dyn Error + Sync + Send>>
+    where
+        N: lsp_types::notification::Notification,
+    {
+        let not = Notification::new(N::METHOD.to_string(), params);
+        self.connection.sender.send(Message::Notification(not))?;
+        Ok(())
+    }
+
+    pub fn send_request<R>(
+        &self,
+        id: RequestId,
+        params: R::Params,
+    ) -> Result<(), Box<dyn Error + Sync + Send>>
+    where
+        R: lsp_types::request::Request,
+    {
+        let req = Request::new(id, R::METHOD.to_string(), params);
+        self.connection.sender.send(Message::Request(req))?;
+        Ok(())
+    }
+
+    // watch_files asks the client to notify about created, changed and deleted scripts so that
+    // the index stays up to date
+    pub fn watch_files(&self) -> Result<(), Box<dyn Error + Sync + Send>> {
+        let options = DidChangeWatchedFilesRegistrationOptions {
+            watchers: vec![FileSystemWatcher {
+                glob_pattern: "**/*.{nasl,inc}".to_string(),
+                kind: None,
+            }],
+        };
+        self.send_request::<RegisterCapability>(
+            RequestId::from(WATCH_FILES.to_string()),
+            RegistrationParams {
+                registrations: vec![Registration {
+                    id: WATCH_FILES.to_string(),
+                    method: DidChangeWatchedFiles::METHOD.to_string(),
+                    register_options: serde_json::to_value(options).ok(),
+                }],
+            },
+        )
+    }
+
+    // publish_diagnostics sends the diagnostics of uri; closed documents have none
+    pub fn publish_diagnostics(
+        &self,
+        cache: &mut Cache,
+        uri: Url,
+    ) -> Result<(), Box<dyn Error + Sync + Send>> {
+        let code = cache.read(uri.path()).unwrap_or_default();
+        let positions = Positions::new(&code, cache.encoding());
+        let diagnostics: Vec<Diagnostic> = cache
+            .diagnostics(uri.path())
+            .iter()
+            .map(|d| d.to_client(&positions).as_diagnostic())
+            .collect();
+        debug!("publishing {} diagnostics for {uri}", diagnostics.len());
+        self.send_notification::<PublishDiagnostics>(PublishDiagnosticsParams {
+            uri,
+            diagnostics,
+            version: None,
+        })
+    }
 }
 
-fn location(path: &str, point: &Point) -> Option<Location> {
-    if let Ok(val) = Url::from_str(&format!("file://{}", path)) {
-        return Some(Location {
-            range: point.as_range(),
-            uri: val,
-        });
+// locations returns the location of the client of each range of a path; each file is read and
+// split into lines once
+fn locations(cache: &Cache, ranges: &[(&str, Point, Point)]) -> Vec<Option<Location>> {
+    let encoding = cache.encoding();
+    let mut codes: HashMap<&str, String> = HashMap::new();
+    if encoding != Encoding::Utf8 {
+        for (path, _, _) in ranges {
+            if !codes.contains_key(path) {
+                codes.insert(path, cache.read(path).unwrap_or_default());
+            }
+        }
+    }
+    let positions: HashMap<&str, Positions> = codes
+        .iter()
+        .map(|(path, code)| (*path, Positions::new(code, encoding)))
+        .collect();
+    let unread = Positions::new("", encoding);
+    ranges
+        .iter()
+        .map(|(path, start, end)| {
+            let uri = Url::from_str(&format!("file://{}", path)).ok()?;
+            let positions = positions.get(path).unwrap_or(&unread);
+            let id = Identifier {
+                start: positions.to_client(*start),
+                end: positions.to_client(*end),
+                identifier: None,
+            };
+            Some(Location {
+                range: id.as_range(),
+                uri,
+            })
+        })
+        .collect()
+}
+
+fn read(cache: &Cache, path: &str) -> Option<String> {
+    match cache.read(path) {
+        Ok(c) => Some(c),
+        Err(err) => {
+            warn!("unable to load {path}: {err}");
+            None
+        }
     }
-    None
 }
 
 impl ToResponseExt<GotoDefinitionParams, GotoDefinitionResponse> for Cache {
@@ -57,46 +214,357 @@ impl ToResponseExt<GotoDefinitionParams, GotoDefinitionResponse> for Cache {
         let line = tdp.position.line as usize;
         let character = tdp.position.character as usize;
         let path = tdp.text_document.uri.path();
-        let code = match NASLInterpreter::read(path) {
-            Ok(c) => Some(c),
-            Err(err) => {
-                warn!("unable to load {path}: {err}");
-                None
-            }
-        }?;
-        let sp = NASLInterpreter::search_parameter(path, &code, line, character)?;
-        let interprets: Vec<NASLInterpreter> =
-            match NASLInterpreter::new_with_includes(path, self.paths.clone(), Some(&code)) {
-                Ok(i) => {
-                    debug!("found {} interpreter", i.len());
-                    i
-                }
-                Err(err) => {
-                    warn!("no interpreter found for {path}: {err}");
-                    vec![]
-                }
-            };
+        let code = read(self, path)?;
+        let column = Positions::new(&code, self.encoding())
+            .to_point(line, character)
+            .column;
+        let sp = self.search_parameter(path, &code, line, column)?;
+        let interprets = self.interpreters(path);
         debug!("looking for {}({line}:{character}) in {path}", sp.name);
-        let mut found: Vec<Location> = interprets
+        let definitions = self.definitions(&interprets, &sp);
+        let ranges: Vec<(&str, Point, Point)> = definitions
             .iter()
-            .flat_map(|i| {
-                let origin = i.clone().origin();
-                i.find_points(&sp)
-                    .filter_map(|i| location(&origin, &i))
-                    .collect::<Vec<Location>>()
-            })
+            .map(|(path, point)| (path.as_str(), *point, *point))
             .collect();
+        let found: Vec<Location> = locations(self, &ranges).into_iter().flatten().collect();
+        debug!("found goto definitions: {:?}", found);
+        Some(GotoDefinitionResponse::Array(found))
+    }
+}
 
-        if found.is_empty() {
-            if let Some(i) = self.internal() {
-                found.extend(
-                    i.find_origin_location(&sp)
-                        .filter_map(|(path, point)| location(&path, &point))
-                        .collect::<Vec<Location>>(),
-                )
+// references returns each usage of name within the interpreters that resolves to one of the
+// given definitions. The definitions within other files are resolved once per file.
+fn references(
+    cache: &Cache,
+    interprets: &[NASLInterpreter],
+    name: &str,
+    definitions: &[(String, Point)],
+) -> Vec<(String, Identifier)> {
+    interprets
+        .iter()
+        .flat_map(|i| {
+            let origin = i.clone().origin();
+            let mut usages = i.find_references(name).peekable();
+            let foreign = match usages.peek() {
+                Some(_) => Cache::foreign_definitions(interprets, &origin, name),
+                None => vec![],
+            };
+            usages
+                .filter(|r| {
+                    let sp = SearchParameter {
+                        origin: &origin,
+                        name,
+                        pos: r.start,
+                    };
+                    cache
+                        .resolve_definitions(interprets, &sp, &foreign)
+                        .iter()
+                        .any(|d| definitions.contains(d))
+                })
+                .map(|r| (origin.clone(), r.clone()))
+                .collect::<Vec<(String, Identifier)>>()
+        })
+        .collect()
+}
+
+impl ToResponseExt<ReferenceParams, Vec<Location>> for Cache {
+    fn handle(&mut self, params: ReferenceParams) -> Option<Vec<Location>> {
+        let tdp = params.text_document_position;
+        let line = tdp.position.line as usize;
+        let character = tdp.position.character as usize;
+        let path = tdp.text_document.uri.path();
+        let code = read(self, path)?;
+        let column = Positions::new(&code, self.encoding())
+            .to_point(line, character)
+            .column;
+        let sp = self.search_parameter(path, &code, line, column)?;
+        debug!(
+            "looking for references of {}({line}:{character}) in {path}",
+            sp.name
+        );
+        let interprets = self.interpreters(path);
+        let definitions = self.definitions(&interprets, &sp);
+        let mut found = references(self, &interprets, sp.name, &definitions);
+        // scripts including a defining file are not reachable via the includes of path
+        let mut seen: Vec<String> = interprets.iter().map(|i| i.clone().origin()).collect();
+        let mut origins: Vec<&String> = definitions.iter().map(|(o, _)| o).collect();
+        origins.sort();
+        origins.dedup();
+        for origin in origins {
+            for p in self.includers(origin) {
+                if !seen.contains(&p) {
+                    let includer = self.interpreters(&p);
+                    found.extend(references(self, &includer, sp.name, &definitions));
+                    seen.push(p);
+                }
             }
         }
-        debug!("found goto definitions: {:?}", found);
-        Some(GotoDefinitionResponse::Array(found))
+        let is_reference =
+            |(o, p): &(String, Point)| found.iter().any(|(ro, r)| ro == o && &r.start == p);
+        let mut ranges: Vec<(&str, Point, Point)> = vec![];
+        if params.context.include_declaration {
+            // assignments are already part of found, function definitions are not
+            ranges.extend(
+                definitions
+                    .iter()
+                    .filter(|d| !is_reference(d))
+                    .map(|(path, point)| (path.as_str(), *point, *point)),
+            );
+        } else {
+            found.retain(|(o, r)| !definitions.contains(&(o.clone(), r.start)));
+        }
+        ranges.extend(found.iter().map(|(o, r)| (o.as_str(), r.start, r.end)));
+        let mut result: Vec<Location> = vec![];
+        for l in locations(self, &ranges).into_iter().flatten() {
+            if !result.contains(&l) {
+                result.push(l);
+            }
+        }
+        debug!("found {} references", result.len());
+        Some(result)
+    }
+}
+
+// definition_markup describes the definition of name at point within origin
+fn definition_markup(cache: &mut Cache, origin: &str, point: &Point, name: &str) -> MarkupContent {
+    let function = cache
+        .interpreter(origin)
+        .ok()
+        .and_then(|i| i.function(name))
+        .filter(|(id, _)| &id.start == point);
+    let signature = match function {
+        Some((_, params)) => {
+            let params: Vec<String> = params.iter().filter_map(|p| p.identifier.clone()).collect();
+            format!("function {name}({})", params.join(", "))
+        }
+        None => name.to_string(),
+    };
+    let file = cache
+        .include_name(origin)
+        .unwrap_or_else(|| origin.to_string());
+    let mut value = format!(
+        "```nasl\n{signature}\n```\n\ndefined in `{file}:{}`",
+        point.row + 1
+    );
+    if let Some(comment) = cache
+        .read(origin)
+        .ok()
+        .and_then(|c| NASLInterpreter::leading_comment(&c, point.row))
+    {
+        value.push_str(&format!("\n\n---\n\n{comment}"));
+    }
+    MarkupContent {
+        kind: MarkupKind::Markdown,
+        value,
+    }
+}
+
+impl ToResponseExt<HoverParams, Hover> for Cache {
+    fn handle(&mut self, params: HoverParams) -> Option<Hover> {
+        let tdp = params.text_document_position_params;
+        let line = tdp.position.line as usize;
+        let character = tdp.position.character as usize;
+        let path = tdp.text_document.uri.path();
+        let code = read(self, path)?;
+        let column = Positions::new(&code, self.encoding())
+            .to_point(line, character)
+            .column;
+        let sp = self.search_parameter(path, &code, line, column)?;
+        let interprets = self.interpreters(path);
+        // user defined functions shadow builtins; resolved like goto definition
+        let definition = self.definitions(&interprets, &sp).into_iter().next();
+        let contents = match definition {
+            Some((origin, point)) => definition_markup(self, &origin, &point, sp.name),
+            None => {
+                let builtin = self.builtin(sp.name)?;
+                debug!("found builtin {} for hover", builtin.name);
+                builtin.as_markup()
+            }
+        };
+        Some(Hover {
+            contents: HoverContents::Markup(contents),
+            range: None,
+        })
+    }
+}
+
+impl ToResponseExt<CompletionParams, CompletionResponse> for Cache {
+    fn handle(&mut self, params: CompletionParams) -> Option<CompletionResponse> {
+        let tdp = params.text_document_position;
+        let line = tdp.position.line as usize;
+        let character = tdp.position.character as usize;
+        let path = tdp.text_document.uri.path();
+        let code = read(self, path)?;
+        let positions = Positions::new(&code, self.encoding());
+        let column = positions.to_point(line, character).column;
+        let items = self
+            .completions(path, line, column)
+            .iter()
+            .map(|c| c.to_client(&positions).as_completion_item())
+            .collect();
+        Some(CompletionResponse::Array(items))
+    }
+}
+
+impl ToResponseExt<SignatureHelpParams, SignatureHelp> for Cache {
+    fn handle(&mut self, params: SignatureHelpParams) -> Option<SignatureHelp> {
+        let tdp = params.text_document_position_params;
+        let line = tdp.position.line as usize;
+        let character = tdp.position.character as usize;
+        let path = tdp.text_document.uri.path();
+        let code = read(self, path)?;
+        let column = Positions::new(&code, self.encoding())
+            .to_point(line, character)
+            .column;
+        let (signature, call) = self.signature(path, line, column)?;
+        debug!("found signature {} for {path}", signature.label());
+        let information = signature.as_signature_information(&call);
+        Some(SignatureHelp {
+            active_parameter: information.active_parameter,
+            signatures: vec![information],
+            active_signature: Some(0),
+        })
+    }
+}
+
+impl ToResponseExt<CodeActionParams, CodeActionResponse> for Cache {
+    fn handle(&mut self, params: CodeActionParams) -> Option<CodeActionResponse> {
+        let uri = params.text_document.uri;
+        let code = read(self, uri.path())?;
+        let positions = Positions::new(&code, self.encoding());
+        let start = positions.to_point(
+            params.range.start.line as usize,
+            params.range.start.character as usize,
+        );
+        let end = positions.to_point(
+            params.range.end.line as usize,
+            params.range.end.character as usize,
+        );
+        let actions: Vec<CodeActionOrCommand> = self
+            .code_actions(uri.path(), start, end)
+            .iter()
+            .map(|a| CodeActionOrCommand::CodeAction(a.to_client(&positions).as_code_action(&uri)))
+            .collect();
+        debug!("found {} code actions for {uri}", actions.len());
+        Some(actions)
+    }
+}
+
+impl ToResponseExt<DocumentSymbolParams, DocumentSymbolResponse> for Cache {
+    fn handle(&mut self, params: DocumentSymbolParams) -> Option<DocumentSymbolResponse> {
+        let path = params.text_document.uri.path();
+        let code = read(self, path)?;
+        let positions = Positions::new(&code, self.encoding());
+        let symbols: Vec<DocumentSymbol> = self
+            .interpreter(path)
+            .ok()?
+            .symbols()
+            .iter()
+            .map(|s| s.to_client(&positions).as_document_symbol())
+            .collect();
+        debug!("found {} symbols for {path}", symbols.len());
+        Some(DocumentSymbolResponse::Nested(symbols))
+    }
+}
+
+impl ToResponseExt<WorkspaceSymbolParams, Vec<SymbolInformation>> for Cache {
+    fn handle(&mut self, params: WorkspaceSymbolParams) -> Option<Vec<SymbolInformation>> {
+        let symbols = self.workspace_symbols(&params.query);
+        let ranges: Vec<(&str, Point, Point)> = symbols
+            .iter()
+            .map(|s| (s.path.as_str(), s.id.start, s.id.end))
+            .collect();
+        Some(
+            symbols
+                .iter()
+                .zip(locations(self, &ranges))
+                .filter_map(|(s, location)| Some(s.as_symbol_information(location?)))
+                .collect(),
+        )
+    }
+}
+
+impl ToResponseExt<ExecuteCommandParams, serde_json::Value> for Cache {
+    fn handle(&mut self, params: ExecuteCommandParams) -> Option<serde_json::Value> {
+        match params.command.as_str() {
+            CACHE_STATISTICS => {
+                let statistics = self.statistics();
+                debug!("cache statistics: {:?}", statistics);
+                Some(serde_json::json!({
+                    "entries": statistics.entries,
+                    "pinned": statistics.pinned,
+                    "limit": statistics.limit,
+                    "hits": statistics.hits,
+                    "misses": statistics.misses,
+                    "hitRate": statistics.hit_rate(),
+                }))
+            }
+            command => {
+                warn!("unknown command {command}");
+                None
+            }
+        }
+    }
+}
+
+impl NotificationExt<DidOpenTextDocumentParams> for Cache {
+    fn notify(&mut self, params: DidOpenTextDocumentParams) {
+        let path = params.text_document.uri.path();
+        debug!("opened {path}");
+        self.open(path, params.text_document.text);
+    }
+}
+
+impl NotificationExt<DidChangeTextDocumentParams> for Cache {
+    fn notify(&mut self, params: DidChangeTextDocumentParams) {
+        let path = params.text_document.uri.path();
+        let encoding = self.encoding();
+        for change in params.content_changes {
+            match (change.range, self.document_mut(path)) {
+                (Some(range), Some(document)) => {
+                    let positions = Positions::new(&document.code, encoding);
+                    let offset =
+                        |p: Position| positions.to_offset(p.line as usize, p.character as usize);
+                    let (start, end) = (offset(range.start), offset(range.end));
+                    document.edit(start, end, &change.text);
+                }
+                (None, _) => self.open(path, change.text),
+                (Some(_), None) => {
+                    warn!("ignoring changes of {path} since it is not opened");
+                    return;
+                }
+            }
+        }
+        if let Some(document) = self.document_mut(path) {
+            document.reparse();
+        }
+        self.outdate(path);
+    }
+}
+
+impl NotificationExt<DidSaveTextDocumentParams> for Cache {
+    fn notify(&mut self, params: DidSaveTextDocumentParams) {
+        let path = params.text_document.uri.path();
+        match params.text {
+            Some(code) => self.open(path, code),
+            None => self.outdate(path),
+        }
+    }
+}
+
+impl NotificationExt<DidChangeWatchedFilesParams> for Cache {
+    fn notify(&mut self, params: DidChangeWatchedFilesParams) {
+        for change in params.changes {
+            debug!("{:?} {}", change.typ, change.uri);
+            self.outdate(change.uri.path());
+        }
+    }
+}
+
+impl NotificationExt<DidCloseTextDocumentParams> for Cache {
+    fn notify(&mut self, params: DidCloseTextDocumentParams) {
+        let path = params.text_document.uri.path();
+        debug!("closed {path}");
+        self.close(path);
     }
 }
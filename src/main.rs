@@ -5,15 +5,27 @@ use std::error::Error;
 use std::fs::File;
 
 use lsp_types::OneOf;
-use lsp_types::{request::GotoDefinition, InitializeParams, ServerCapabilities};
+use lsp_types::{
+    notification::{
+        DidChangeTextDocument, DidCloseTextDocument, DidOpenTextDocument, DidSaveTextDocument,
+    },
+    request::{
+        CodeActionRequest, Completion, DocumentSymbolRequest, ExecuteCommand, GotoDefinition,
+        HoverRequest, References, SignatureHelpRequest, WorkspaceSymbol,
+    },
+    CodeActionProviderCapability, CompletionOptions, DidChangeWatchedFilesParams,
+    ExecuteCommandOptions, HoverProviderCapability, InitializeParams, ServerCapabilities,
+    SignatureHelpOptions, TextDocumentSyncCapability, TextDocumentSyncKind,
+    TextDocumentSyncOptions, TextDocumentSyncSaveOptions, Url,
+};
 
-use nasl::cache::Cache;
+use nasl::{cache::Cache, position::Encoding};
 
-use lsp_server::{Connection, ExtractError, Message, Request, RequestId};
+use lsp_server::{Connection, ExtractError, Message, Notification, Request, RequestId};
 use tracing::{debug, info, Level};
 
 use crate::extension::Settings;
-use crate::handler::RequestResponseSender;
+use crate::handler::{NotificationExt, RequestResponseSender, TextDocumentExt, CACHE_STATISTICS};
 
 fn main() -> Result<(), Box<dyn Error + Sync + Send>> {
     let home = env::var("HOME")?;
@@ -27,21 +39,54 @@ fn main() -> Result<(), Box<dyn Error + Sync + Send>> {
     info!("Starting nasl-analyzer");
     let (connection, io_threads) = Connection::stdio();
     let (id, params) = connection.initialize_start()?;
+    // positionEncodings is not supported by lsp_types yet
+    let encodings: Vec<String> = params
+        .pointer("/capabilities/general/positionEncodings")
+        .and_then(|e| serde_json::from_value(e.clone()).ok())
+        .unwrap_or_default();
+    let encoding = Encoding::negotiate(&encodings);
     let init_params: InitializeParams = serde_json::from_value(params).unwrap();
     let server_capabilities = ServerCapabilities {
+        text_document_sync: Some(TextDocumentSyncCapability::Options(
+            TextDocumentSyncOptions {
+                open_close: Some(true),
+                change: Some(TextDocumentSyncKind::INCREMENTAL),
+                save: Some(TextDocumentSyncSaveOptions::Supported(true)),
+                ..Default::default()
+            },
+        )),
         definition_provider: Some(OneOf::Left(true)),
+        references_provider: Some(OneOf::Left(true)),
+        hover_provider: Some(HoverProviderCapability::Simple(true)),
+        completion_provider: Some(CompletionOptions {
+            trigger_characters: Some(vec!["\"".to_string(), "/".to_string()]),
+            ..Default::default()
+        }),
+        signature_help_provider: Some(SignatureHelpOptions {
+            trigger_characters: Some(vec!["(".to_string(), ",".to_string()]),
+            ..Default::default()
+        }),
+        code_action_provider: Some(CodeActionProviderCapability::Simple(true)),
+        document_symbol_provider: Some(OneOf::Left(true)),
+        workspace_symbol_provider: Some(OneOf::Left(true)),
+        execute_command_provider: Some(ExecuteCommandOptions {
+            commands: vec![CACHE_STATISTICS.to_string()],
+            ..Default::default()
+        }),
         ..Default::default()
     };
 
+    let mut capabilities = serde_json::to_value(server_capabilities)?;
+    capabilities["positionEncoding"] = encoding.as_str().into();
     let initialize_data = serde_json::json!({
-        "capabilities": server_capabilities,
+        "capabilities": capabilities,
         "serverInfo": {
           "name": "nasl-analyzer",
           "version": "0.1",
     }});
     connection.initialize_finish(id, initialize_data)?;
 
-    main_loop(connection, init_params)?;
+    main_loop(connection, init_params, encoding)?;
     io_threads.join()?;
 
     info!("shutting down server");
@@ -51,16 +96,29 @@ fn main() -> Result<(), Box<dyn Error + Sync + Send>> {
 fn main_loop(
     connection: Connection,
     init_params: InitializeParams,
+    encoding: Encoding,
 ) -> Result<(), Box<dyn Error + Sync + Send>> {
     let rp: Vec<String> = init_params
         .workspace_folders
         .map(|i| i.iter().map(|i| i.uri.to_string()).collect())
         .unwrap_or_default();
     let mut cache = Cache::new(rp.clone());
+    cache.set_encoding(encoding);
+    cache.build_index();
+    debug!("using position encoding {}", encoding.as_str());
     let rrs = RequestResponseSender {
         connection: &connection,
     };
     debug!("Initialized cache for {:?}", rp);
+    let watch = init_params
+        .capabilities
+        .workspace
+        .and_then(|w| w.did_change_watched_files)
+        .and_then(|d| d.dynamic_registration)
+        .unwrap_or_default();
+    if watch {
+        rrs.watch_files()?;
+    }
     for msg in &connection.receiver {
         match msg {
             Message::Request(req) => {
@@ -68,7 +126,71 @@ fn main_loop(
                     return Ok(());
                 }
 
-                match cast::<GotoDefinition>(req) {
+                let req = match cast::<GotoDefinition>(req) {
+                    Ok((id, params)) => {
+                        rrs.send_response(&mut cache, params, id)?;
+                        continue;
+                    }
+                    Err(err @ ExtractError::JsonError { .. }) => panic!("{:?}", err),
+                    Err(ExtractError::MethodMismatch(req)) => req,
+                };
+                let req = match cast::<References>(req) {
+                    Ok((id, params)) => {
+                        rrs.send_response(&mut cache, params, id)?;
+                        continue;
+                    }
+                    Err(err @ ExtractError::JsonError { .. }) => panic!("{:?}", err),
+                    Err(ExtractError::MethodMismatch(req)) => req,
+                };
+                let req = match cast::<HoverRequest>(req) {
+                    Ok((id, params)) => {
+                        rrs.send_response(&mut cache, params, id)?;
+                        continue;
+                    }
+                    Err(err @ ExtractError::JsonError { .. }) => panic!("{:?}", err),
+                    Err(ExtractError::MethodMismatch(req)) => req,
+                };
+                let req = match cast::<Completion>(req) {
+                    Ok((id, params)) => {
+                        rrs.send_response(&mut cache, params, id)?;
+                        continue;
+                    }
+                    Err(err @ ExtractError::JsonError { .. }) => panic!("{:?}", err),
+                    Err(ExtractError::MethodMismatch(req)) => req,
+                };
+                let req = match cast::<SignatureHelpRequest>(req) {
+                    Ok((id, params)) => {
+                        rrs.send_response(&mut cache, params, id)?;
+                        continue;
+                    }
+                    Err(err @ ExtractError::JsonError { .. }) => panic!("{:?}", err),
+                    Err(ExtractError::MethodMismatch(req)) => req,
+                };
+                let req = match cast::<CodeActionRequest>(req) {
+                    Ok((id, params)) => {
+                        rrs.send_response(&mut cache, params, id)?;
+                        continue;
+                    }
+                    Err(err @ ExtractError::JsonError { .. }) => panic!("{:?}", err),
+                    Err(ExtractError::MethodMismatch(req)) => req,
+                };
+                let req = match cast::<DocumentSymbolRequest>(req) {
+                    Ok((id, params)) => {
+                        rrs.send_response(&mut cache, params, id)?;
+                        continue;
+                    }
+                    Err(err @ ExtractError::JsonError { .. }) => panic!("{:?}", err),
+                    Err(ExtractError::MethodMismatch(req)) => req,
+                };
+                let req = match cast::<WorkspaceSymbol>(req) {
+                    Ok((id, params)) => {
+                        rrs.send_response(&mut cache, params, id)?;
+                        continue;
+                    }
+                    Err(err @ ExtractError::JsonError { .. }) => panic!("{:?}", err),
+                    Err(ExtractError::MethodMismatch(req)) => req,
+                };
+                match cast::<ExecuteCommand>(req) {
                     Ok((id, params)) => {
                         rrs.send_response(&mut cache, params, id)?;
                         continue;
@@ -76,21 +198,47 @@ fn main_loop(
                     Err(err @ ExtractError::JsonError { .. }) => panic!("{:?}", err),
                     Err(ExtractError::MethodMismatch(req)) => req,
                 };
-                // match cast::<References>(req) {
-                //     Ok((id, params)) => {
-                //         rrs.send_response(&mut cache, params, id)?;
-                //         continue;
-                //     }
-                //     Err(err @ ExtractError::JsonError { .. }) => panic!("{:?}", err),
-                //     Err(ExtractError::MethodMismatch(req)) => req,
-                // };
                 // ...
             }
             Message::Response(resp) => {
                 debug!("got response: {:?}", resp);
             }
             Message::Notification(not) => {
-                if not.method == "workspace/didChangeConfiguration" {
+                let not = match notify::<DidOpenTextDocument>(&mut cache, not) {
+                    Ok(uri) => {
+                        rrs.publish_diagnostics(&mut cache, uri)?;
+                        continue;
+                    }
+                    Err(not) => not,
+                };
+                let not = match notify::<DidChangeTextDocument>(&mut cache, not) {
+                    Ok(uri) => {
+                        rrs.publish_diagnostics(&mut cache, uri)?;
+                        continue;
+                    }
+                    Err(not) => not,
+                };
+                let not = match notify::<DidSaveTextDocument>(&mut cache, not) {
+                    Ok(uri) => {
+                        rrs.publish_diagnostics(&mut cache, uri)?;
+                        continue;
+                    }
+                    Err(not) => not,
+                };
+                let not = match notify::<DidCloseTextDocument>(&mut cache, not) {
+                    Ok(uri) => {
+                        rrs.publish_diagnostics(&mut cache, uri)?;
+                        continue;
+                    }
+                    Err(not) => not,
+                };
+                if not.method == "workspace/didChangeWatchedFiles" {
+                    let changes: Result<DidChangeWatchedFilesParams, serde_json::Error> =
+                        serde_json::from_value(not.params);
+                    if let Ok(changes) = changes {
+                        cache.notify(changes);
+                    }
+                } else if not.method == "workspace/didChangeConfiguration" {
                     let set: Result<Settings, serde_json::Error> =
                         serde_json::from_value(not.clone().params);
                     if let Ok(set) = set {
@@ -102,6 +250,9 @@ fn main_loop(
                             if let Some(osc) = paths.openvas {
                                 cache.set_internal(&osc);
                             }
+                            if let Some(size) = paths.cache_size {
+                                cache.set_limit(size);
+                            }
                         }
                     }
                 } else {
@@ -120,3 +271,22 @@ where
 {
     req.extract(R::METHOD)
 }
+
+// notify forwards the notification to cache when it is of type N and returns the uri of the
+// affected document, otherwise the notification is returned
+fn notify<N>(cache: &mut Cache, not: Notification) -> Result<Url, Notification>
+where
+    N: lsp_types::notification::Notification,
+    N::Params: serde::de::DeserializeOwned + TextDocumentExt,
+    Cache: NotificationExt<N::Params>,
+{
+    match not.extract::<N::Params>(N::METHOD) {
+        Ok(params) => {
+            let uri = params.uri().clone();
+            cache.notify(params);
+            Ok(uri)
+        }
+        Err(err @ ExtractError::JsonError { .. }) => panic!("{:?}", err),
+        Err(ExtractError::MethodMismatch(not)) => Err(not),
+    }
+}